@@ -0,0 +1,223 @@
+//! Aiming launchers within their limits, with the mouse or with the keyboard.
+//!
+//! Dragging back from a selected launcher aims it like a slingshot. Tab picks one of your
+//! launchers for fine aim instead, where the arrow keys turn it and change the power a
+//! little at a time and space fires.
+
+use std::f32::consts::FRAC_PI_2;
+
+use bevy::{math::I64Vec2, prelude::*, sprite::Anchor};
+
+use crate::fixed::{self, Rotation};
+use crate::input::TickInput;
+use crate::launcher::{Launcher, fire};
+use crate::{ActivePlayer, Battlefield, CANNONBALL_SIZE, Grid, Player, PlayerSide, Turn};
+
+const BARREL_SIZE: Vec2 = Vec2::new(6.0, 28.0);
+const BARREL_COLOR: Color = Color::srgb(0.2, 0.2, 0.22);
+const FINE_AIM_BARREL_COLOR: Color = Color::srgb(0.95, 0.8, 0.2);
+const RELOADING_BARREL_COLOR: Color = Color::srgb(0.5, 0.5, 0.5);
+/// in front of the launcher's own sprite
+const BARREL_Z: f32 = 0.1;
+/// how far out of the launcher's middle shots start, clear of its own cells, in sub-cells
+pub const MUZZLE_DISTANCE: i64 = (BARREL_SIZE.y + CANNONBALL_SIZE.x) as i64 * fixed::PER_PIXEL;
+/// mouse drag distance for full power
+pub const FULL_POWER_DRAG: f32 = 100.0;
+/// in thousandths of a radian
+const FINE_ANGLE_STEP: i64 = 10;
+/// in percent
+const FINE_POWER_STEP: i64 = 1;
+
+/// Where a launcher is pointing, in whole numbers so every machine fires the same shot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Aim {
+    /// thousandths of a radian from straight at the enemy side, counter-clockwise
+    pub angle: i64,
+    /// percent of full speed
+    pub power: i64,
+}
+
+/// How far a kind of launcher can turn and how hard it can fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AimLimits {
+    /// largest angle either side of straight at the enemy
+    pub max_angle: i64,
+    pub min_power: i64,
+    pub max_power: i64,
+}
+
+impl AimLimits {
+    pub fn clamp(&self, aim: Aim) -> Aim {
+        Aim {
+            angle: aim.angle.clamp(-self.max_angle, self.max_angle),
+            power: aim.power.clamp(self.min_power, self.max_power),
+        }
+    }
+
+    /// straight at the enemy with half of the power range
+    pub fn default_aim(&self) -> Aim {
+        Aim {
+            angle: 0,
+            power: (self.min_power + self.max_power) / 2,
+        }
+    }
+}
+
+/// `length` sub-cells along `angle` for a launcher on `side`
+pub fn aim_offset(side: PlayerSide, angle: i64, length: i64) -> I64Vec2 {
+    Rotation::from_milliradians(angle).rotate(-side.down() * length)
+}
+
+/// world direction of `angle` for a launcher on `side`, only for drawing
+pub fn aim_direction(side: PlayerSide, angle: i64) -> Vec2 {
+    let up = -side.down().as_vec2();
+    Vec2::from_angle(angle as f32 / fixed::MILLIRADIANS as f32).rotate(up)
+}
+
+/// The part of a launcher that turns to show its aim.
+#[derive(Component)]
+pub struct Barrel;
+
+/// The launcher the arrow keys are aiming.
+#[derive(Component)]
+pub struct FineAim;
+
+pub fn spawn_barrel(parent: &mut ChildSpawnerCommands) {
+    parent.spawn((
+        Sprite {
+            color: BARREL_COLOR,
+            custom_size: Some(BARREL_SIZE),
+            anchor: Anchor::BottomCenter,
+            ..default()
+        },
+        Transform::from_xyz(0.0, 0.0, BARREL_Z),
+        Barrel,
+    ));
+}
+
+/// point the selected launcher away from the mouse, like pulling back a slingshot
+pub fn aim_with_mouse(
+    mut launchers: Query<(&mut Launcher, &Transform), Without<FineAim>>,
+    input: Res<TickInput>,
+) {
+    let Some(world_position) = input.world_cursor else {
+        return;
+    };
+    for (mut launcher, transform) in &mut launchers {
+        if !launcher.is_selected {
+            continue;
+        }
+        let pull = transform.translation.truncate() - world_position;
+        if pull == Vec2::ZERO {
+            continue;
+        }
+        // rounded here, the simulation only ever sees whole numbers
+        let up = aim_direction(launcher.player_side, 0);
+        let aim = Aim {
+            angle: (up.angle_to(pull) * fixed::MILLIRADIANS as f32).round() as i64,
+            power: (pull.length() / FULL_POWER_DRAG * 100.0).round() as i64,
+        };
+        let aim = launcher.kind.aim_limits().clamp(aim);
+        if launcher.aim != aim {
+            launcher.aim = aim;
+        }
+    }
+}
+
+/// Tab picks the next launcher, arrows adjust it, space fires and escape stops
+pub fn fine_aim(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    turn: Single<&Turn>,
+    active: Res<ActivePlayer>,
+    mut launchers: Query<(Entity, &mut Launcher, &Grid, Has<FineAim>)>,
+    mut players: Query<&mut Player>,
+    battlefield: Res<Battlefield>,
+) {
+    if input.keys.just_pressed(KeyCode::Tab) {
+        let own: Vec<(Entity, bool)> = launchers
+            .iter()
+            .filter(|(_, launcher, ..)| launcher.player_side == turn.player_side)
+            .map(|(e, _, _, fine)| (e, fine))
+            .collect();
+        // tabbing past the last launcher turns fine aim off
+        let next = own.iter().position(|(_, fine)| *fine).map_or(0, |i| i + 1);
+        for (e, ..) in &launchers {
+            commands.entity(e).remove::<FineAim>();
+        }
+        if let Some((e, _)) = own.get(next) {
+            commands.entity(*e).insert(FineAim);
+        }
+        return;
+    }
+
+    let Some((e, mut launcher, grid, _)) = launchers.iter_mut().find(|(.., fine)| *fine) else {
+        return;
+    };
+    if input.keys.just_pressed(KeyCode::Escape) {
+        commands.entity(e).remove::<FineAim>();
+        return;
+    }
+    if input.keys.just_pressed(KeyCode::Space) {
+        let Ok(mut player) = players.get_mut(**active) else {
+            return;
+        };
+        if player.side != launcher.player_side {
+            return;
+        }
+        fire(
+            &mut commands,
+            &battlefield,
+            &mut launcher,
+            fixed::center(grid),
+            &mut player,
+        );
+        commands.entity(e).remove::<FineAim>();
+        return;
+    }
+
+    let mut aim = launcher.aim;
+    // a bigger angle turns counter-clockwise, which is left on either player's screen
+    if input.keys.pressed(KeyCode::ArrowLeft) {
+        aim.angle += FINE_ANGLE_STEP;
+    }
+    if input.keys.pressed(KeyCode::ArrowRight) {
+        aim.angle -= FINE_ANGLE_STEP;
+    }
+    if input.keys.pressed(KeyCode::ArrowUp) {
+        aim.power += FINE_POWER_STEP;
+    }
+    if input.keys.pressed(KeyCode::ArrowDown) {
+        aim.power -= FINE_POWER_STEP;
+    }
+    let aim = launcher.kind.aim_limits().clamp(aim);
+    if launcher.aim != aim {
+        launcher.aim = aim;
+    }
+}
+
+/// turn each barrel to its launcher's aim, highlighting the one being fine aimed and
+/// greying out the ones reloading
+pub fn update_barrels(
+    launchers: Query<(&Launcher, &Children, Has<FineAim>)>,
+    mut barrels: Query<(&mut Transform, &mut Sprite), With<Barrel>>,
+) {
+    for (launcher, children, fine) in &launchers {
+        let direction = aim_direction(launcher.player_side, launcher.aim.angle);
+        for child in children.iter() {
+            let Ok((mut transform, mut sprite)) = barrels.get_mut(child) else {
+                continue;
+            };
+            transform.rotation = Quat::from_rotation_z(direction.to_angle() - FRAC_PI_2);
+            // longer barrel for more power
+            transform.scale.y = 0.5 + launcher.aim.power as f32 / 200.0;
+            sprite.color = if fine {
+                FINE_AIM_BARREL_COLOR
+            } else if launcher.reload > 0 {
+                RELOADING_BARREL_COLOR
+            } else {
+                BARREL_COLOR
+            };
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! The camera, turned around to face whoever's turn it is.
+
+use bevy::prelude::*;
+
+use crate::{GameSet, MainCamera, TurnStarted};
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TurnStarted>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(FixedUpdate, turn_camera.in_set(GameSet::Presentation));
+    }
+}
+
+fn spawn_camera(mut commands: Commands) {
+    commands.spawn((Camera2d, MainCamera));
+}
+
+/// rotate the camera 180° every turn so each player looks at the map from their side
+fn turn_camera(
+    mut turns: EventReader<TurnStarted>,
+    mut camera: Single<&mut Transform, With<MainCamera>>,
+) {
+    for _ in turns.read() {
+        camera.rotate(Quat::from_rotation_z(std::f32::consts::PI));
+    }
+}
@@ -0,0 +1,524 @@
+//! Everything that hits and breaks things: the weather, aiming and firing launchers,
+//! shots flying and bouncing, blasts and fires, and how the damage is shown.
+
+use bevy::ecs::query::QueryData;
+use bevy::ecs::system::SystemParam;
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::aim;
+use crate::defense::{self, Deflector};
+use crate::economy::{self, Damaged, StructureDestroyed, report_damage};
+use crate::fire::{self, Burning, catch_fire};
+use crate::fixed::{self, Position};
+use crate::friendly_fire::{FriendlyFire, OwnHit};
+use crate::input::TickInput;
+use crate::launcher::{self, Guided, Launcher};
+use crate::structures::Structure;
+use crate::weather::{self, Meteor, Soaked, Weather, WeatherSettings};
+use crate::{
+    ActivePlayer, AmmoType, BOARD_DEBRIS_COLOR, Battlefield, Block, Board, Breakable,
+    CANNONBALL_SIZE, CannonBall, Collider, CollisionResponse, Explosion, GameSet, Grid, Player,
+    PlayerSide, Turn, TurnStarted, Velocity,
+};
+
+/// damage is multiplied by this when a structure is hit on a weak point
+const WEAK_POINT_MULTIPLIER: u8 = 2;
+/// rain softens dirt this much
+const SOAKED_MULTIPLIER: u8 = 2;
+
+/// cells around a TNT block (in every direction) hit when it goes off
+const TNT_BLAST_RADIUS: i64 = 2;
+
+/// tint of a block that is one hit from breaking, intact blocks are untinted
+const DAMAGED_TINT: Color = Color::srgb(0.4, 0.35, 0.35);
+const HIT_FLASH_COLOR: Color = Color::srgb(1.0, 0.3, 0.3);
+const HIT_FLASH_SECONDS: f32 = 0.15;
+const FIRE_TINT: Color = Color::srgb(1.0, 0.45, 0.1);
+const FIRE_FLICKER_SPEED: f32 = 12.0;
+
+const DEBRIS_PIECES: usize = 6;
+const DEBRIS_SIZE: Vec2 = Vec2::new(4.0, 4.0);
+const DEBRIS_SPEED: f32 = 2.0;
+const DEBRIS_GRAVITY: f32 = 0.15;
+const DEBRIS_SECONDS: f32 = 0.6;
+
+/// half a shot's width in sub-cells, for hitting things
+const SHOT_HALF_SIZE: i64 = CANNONBALL_SIZE.x as i64 * fixed::PER_PIXEL / 2;
+/// shots slowed down below this many sub-cells a tick by what they hit are used up
+const MIN_SHOT_SPEED: i64 = 2 * fixed::PER_PIXEL;
+/// percent of a shot's speed kept bouncing off the side walls
+const EDGE_RESTITUTION: i64 = 80;
+const DEFLECTOR_RESTITUTION: i64 = 100;
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TurnStarted>()
+            .add_event::<Explosion>()
+            .add_event::<Damaged>()
+            .add_event::<StructureDestroyed>()
+            .init_resource::<FriendlyFire>()
+            .init_resource::<WeatherSettings>()
+            .init_resource::<Weather>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    select_launcher,
+                    aim::aim_with_mouse.after(select_launcher),
+                    fire_selected_launcher.after(aim::aim_with_mouse),
+                    aim::fine_aim,
+                )
+                    .in_set(GameSet::Commands),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    weather::change_weather,
+                    weather::blow_wind.after(weather::change_weather),
+                    weather::meteor_shower.after(weather::change_weather),
+                    launcher::guide_rockets,
+                    apply_velocity
+                        .after(weather::blow_wind)
+                        .after(launcher::guide_rockets),
+                    launcher::fly_shells,
+                    bounce_off_edges.after(apply_velocity),
+                    defense::recharge_shields,
+                )
+                    .in_set(GameSet::Simulation),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    weather::rain,
+                    // so burns and strikes always count towards this turn's damage
+                    weather::lightning.before(economy::track_damage),
+                    fire::burn
+                        .after(weather::rain)
+                        .before(economy::track_damage),
+                    defense::shield_shots.before(cannonball_break_stuff),
+                    defense::point_defense.before(cannonball_break_stuff),
+                    cannonball_break_stuff,
+                    launcher::fire_lasers,
+                    explode
+                        .after(cannonball_break_stuff)
+                        .after(launcher::fire_lasers),
+                )
+                    .in_set(GameSet::Resolution),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    aim::update_barrels,
+                    fixed::place_transforms,
+                    launcher::fade_beams,
+                    defense::show_shield_charges,
+                    show_damage,
+                    update_debris,
+                )
+                    .in_set(GameSet::Presentation),
+            );
+    }
+}
+
+/// Briefly tints a block after it's hit.
+#[derive(Component, Deref, DerefMut)]
+struct HitFlash(Timer);
+
+impl Default for HitFlash {
+    fn default() -> Self {
+        HitFlash(Timer::from_seconds(HIT_FLASH_SECONDS, TimerMode::Once))
+    }
+}
+
+/// A bit of a broken block flying off, removed once its timer runs out. It's only for
+/// show so it moves in pixels.
+#[derive(Component)]
+struct Debris {
+    timer: Timer,
+    velocity: Vec2,
+}
+
+fn apply_velocity(mut query: Query<(&Velocity, &mut Position)>) {
+    query.iter_mut().for_each(|(velocity, mut position)| {
+        **position += **velocity;
+    });
+}
+
+pub fn fire_selected_launcher(
+    mut commands: Commands,
+    mut launcher_query: Query<(&mut Launcher, &Grid)>,
+    input: Res<TickInput>,
+    active: Res<ActivePlayer>,
+    mut player_query: Query<&mut Player>,
+    battlefield: Res<Battlefield>,
+) {
+    if !input.mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let Ok(mut player) = player_query.get_mut(**active) else {
+        return;
+    };
+
+    for (mut launcher, grid) in launcher_query.iter_mut() {
+        if launcher.is_selected {
+            launcher.is_selected = false;
+            launcher::fire(
+                &mut commands,
+                &battlefield,
+                &mut launcher,
+                fixed::center(grid),
+                &mut player,
+            );
+        }
+    }
+}
+
+fn select_launcher(
+    mut launcher_query: Query<(&mut Launcher, &Grid)>,
+    input: Res<TickInput>,
+    battlefield: Res<Battlefield>,
+    turn: Single<&Turn>,
+) {
+    if !input.mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+    if let Some(grid_position) = input.cell(&battlefield) {
+        for (mut launcher, grid) in launcher_query.iter_mut() {
+            let collided = grid.positions.contains(&grid_position);
+
+            launcher.is_selected = collided && (launcher.player_side == turn.player_side);
+            if launcher.is_selected {
+                debug!("selected the {} at {grid_position}", launcher.kind.name());
+            }
+        }
+    }
+}
+
+/// whether a shot at `position` touches `cell`
+fn shot_touches(position: I64Vec2, cell: I64Vec2) -> bool {
+    let reach = SHOT_HALF_SIZE + fixed::CELL / 2;
+    (position - fixed::cell_center(cell)).abs().max_element() <= reach
+}
+
+/// which side of a cell something touching it is on, as a unit vector along one axis
+fn contact_normal(point: I64Vec2, cell: I64Vec2) -> I64Vec2 {
+    let offset = point - fixed::cell_center(cell);
+    if offset.x.abs() > offset.y.abs() {
+        I64Vec2::new(offset.x.signum(), 0)
+    } else {
+        I64Vec2::new(0, offset.y.signum())
+    }
+}
+
+/// Something a shot or a blast can damage.
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct BreakableTarget {
+    pub entity: Entity,
+    pub transform: &'static Transform,
+    pub breakable: &'static mut Breakable,
+    pub grid: &'static Grid,
+    pub block: Option<&'static Block>,
+    pub board: &'static Board,
+    pub structure: Option<&'static Structure>,
+    pub deflector: Has<Deflector>,
+    pub burning: Has<Burning>,
+    pub soaked: Has<Soaked>,
+}
+
+/// The events sent when something loses health.
+#[derive(SystemParam)]
+pub struct DamageEvents<'w> {
+    damaged: EventWriter<'w, Damaged>,
+    destroyed: EventWriter<'w, StructureDestroyed>,
+}
+
+/// how much `ammo` takes off the target, more when it hits a weak point or soaked dirt
+pub fn hit_damage(target: &BreakableTargetItem, ammo: AmmoType, weak: bool) -> u8 {
+    let mut damage = target
+        .block
+        .map_or(1, |block| block.block_type.damage_taken(ammo));
+    if weak {
+        damage = damage.saturating_mul(WEAK_POINT_MULTIPLIER);
+    }
+    if target.soaked {
+        damage = damage.saturating_mul(SOAKED_MULTIPLIER);
+    }
+    damage
+}
+
+/// take `damage` off the target and knock it down at 0 health, true if it broke
+pub fn damage_target(
+    commands: &mut Commands,
+    events: &mut DamageEvents,
+    target: &mut BreakableTargetItem,
+    damage: u8,
+    attacker: Option<PlayerSide>,
+) -> bool {
+    let health_before = target.breakable.health;
+    target.breakable.health = target.breakable.health.saturating_sub(damage);
+    report_damage(
+        health_before,
+        &target.breakable,
+        target.board,
+        target.structure,
+        attacker,
+        &mut events.damaged,
+        &mut events.destroyed,
+    );
+    if damage > 0 {
+        commands.entity(target.entity).insert(HitFlash::default());
+    }
+    if target.breakable.health > 0 {
+        return false;
+    }
+    commands.entity(target.entity).despawn();
+    spawn_debris(
+        commands,
+        target.transform.translation.truncate(),
+        target.block,
+    );
+    true
+}
+
+type Flying<'a> = (
+    Entity,
+    &'a Position,
+    &'a mut CannonBall,
+    &'a mut Velocity,
+    Has<Meteor>,
+);
+
+fn cannonball_break_stuff(
+    mut commands: Commands,
+    mut cannonball_q: Query<Flying, With<Collider>>,
+    mut breakable_q: Query<BreakableTarget>,
+    friendly_fire: Res<FriendlyFire>,
+    mut explosions: EventWriter<Explosion>,
+    mut events: DamageEvents,
+) {
+    for (cannonball_e, position, mut cannonball, mut velocity, meteor) in cannonball_q.iter_mut() {
+        let ball_center = **position;
+        // meteors are nobody's, whatever they knock down doesn't earn a bounty
+        let attacker = (!meteor).then_some(cannonball.player_side);
+
+        for mut target in breakable_q.iter_mut() {
+            // already broken by another ball this frame, or what this one is still getting
+            // past
+            if target.breakable.health == 0 || cannonball.last_hit == Some(target.entity) {
+                continue;
+            }
+            // check every covered cell so odd shapes aren't hit through their gaps
+            let Some(cell) = target
+                .grid
+                .positions
+                .iter()
+                .copied()
+                .find(|cell| shot_touches(ball_center, *cell))
+            else {
+                continue;
+            };
+
+            let weak = target.grid.weak_points.contains(&cell);
+            let mut damage = hit_damage(&target, cannonball.ammo, weak);
+            let mut response = if target.deflector {
+                CollisionResponse::Ricochet {
+                    restitution: DEFLECTOR_RESTITUTION,
+                }
+            } else {
+                target.block.map_or(CollisionResponse::Stop, |block| {
+                    block.block_type.response(cannonball.ammo)
+                })
+            };
+            if target.board.player_side == cannonball.player_side {
+                match friendly_fire.own_hit(ball_center - cannonball.origin) {
+                    OwnHit::Ignored => continue,
+                    OwnHit::Absorbed => {
+                        damage = 0;
+                        response = CollisionResponse::Stop;
+                    }
+                    OwnHit::Damaged => {}
+                }
+            }
+            if target.deflector {
+                // it goes back out belonging to the deflector's owner, and unharmed
+                damage = 0;
+                cannonball.player_side = target.board.player_side;
+                cannonball.origin = ball_center;
+                commands.entity(cannonball_e).remove::<Guided>();
+            }
+
+            if damage_target(&mut commands, &mut events, &mut target, damage, attacker)
+                && target
+                    .block
+                    .is_some_and(|block| block.block_type.explodes())
+            {
+                explosions.write(Explosion {
+                    position: target.grid.positions[0],
+                    attacker,
+                    origin: cannonball.origin,
+                });
+            }
+            if damage > 0 {
+                catch_fire(&mut commands, &target, cannonball.ammo);
+            }
+            cannonball.last_hit = Some(target.entity);
+            match response {
+                CollisionResponse::Stop => **velocity = I64Vec2::ZERO,
+                CollisionResponse::Ricochet { restitution } => {
+                    let normal = contact_normal(ball_center, cell);
+                    let along = velocity.dot(normal);
+                    if along < 0 {
+                        **velocity -= normal * 2 * along;
+                    }
+                    **velocity = fixed::scale(**velocity, restitution);
+                }
+                CollisionResponse::Penetrate { retained } => {
+                    **velocity = fixed::scale(**velocity, retained)
+                }
+            }
+            if velocity.length_squared() < MIN_SHOT_SPEED.pow(2) {
+                commands.entity(cannonball_e).despawn();
+            }
+            // one hit per tick
+            break;
+        }
+    }
+}
+
+/// side walls bounce shots back in, past either end of the map they're gone
+fn bounce_off_edges(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    mut shots: Query<(Entity, &mut Position, &mut Velocity), With<CannonBall>>,
+) {
+    let width = battlefield.width as i64 * fixed::CELL;
+    let height = battlefield.height as i64 * fixed::CELL;
+    for (e, mut position, mut velocity) in &mut shots {
+        if position.y < 0 || position.y > height {
+            commands.entity(e).despawn();
+            continue;
+        }
+        let past_left = position.x < 0 && velocity.x < 0;
+        let past_right = position.x > width && velocity.x > 0;
+        if past_left || past_right {
+            velocity.x = -velocity.x;
+            **velocity = fixed::scale(**velocity, EDGE_RESTITUTION);
+            position.x = position.x.clamp(0, width);
+        }
+    }
+}
+
+/// damage everything around each explosion, TNT caught in the blast chains
+pub fn explode(
+    mut commands: Commands,
+    mut explosions: EventReader<Explosion>,
+    mut breakable_q: Query<BreakableTarget>,
+    friendly_fire: Res<FriendlyFire>,
+    mut events: DamageEvents,
+) {
+    let mut pending: Vec<(I64Vec2, Option<PlayerSide>, I64Vec2)> = explosions
+        .read()
+        .map(|e| (e.position, e.attacker, e.origin))
+        .collect();
+    while let Some((center, attacker, origin)) = pending.pop() {
+        for mut target in &mut breakable_q {
+            if target.breakable.health == 0 {
+                continue;
+            }
+            let reaches = |p: &I64Vec2| (*p - center).abs().max_element() <= TNT_BLAST_RADIUS;
+            if !target.grid.positions.iter().any(reaches) {
+                continue;
+            }
+            // the blast follows the same rules as the shot that set it off
+            if attacker == Some(target.board.player_side)
+                && friendly_fire.own_hit(fixed::cell_center(center) - origin) != OwnHit::Damaged
+            {
+                continue;
+            }
+            let weak = target.grid.weak_points.iter().any(reaches);
+            let damage = hit_damage(&target, AmmoType::Blast, weak);
+            if damage_target(&mut commands, &mut events, &mut target, damage, attacker)
+                && target
+                    .block
+                    .is_some_and(|block| block.block_type.explodes())
+            {
+                pending.push((target.grid.positions[0], attacker, origin));
+            }
+            if damage > 0 {
+                catch_fire(&mut commands, &target, AmmoType::Blast);
+            }
+        }
+    }
+}
+
+/// burst of small pieces flying out from a block that just broke
+fn spawn_debris(commands: &mut Commands, position: Vec2, block: Option<&Block>) {
+    let color = block.map_or(BOARD_DEBRIS_COLOR, |block| block.block_type.debris_color());
+    for i in 0..DEBRIS_PIECES {
+        let angle = std::f32::consts::TAU * i as f32 / DEBRIS_PIECES as f32;
+        // alternate fast and slow pieces so the burst isn't a perfect ring
+        let speed = DEBRIS_SPEED * if i % 2 == 0 { 1.0 } else { 0.6 };
+        commands.spawn((
+            Sprite {
+                color,
+                custom_size: Some(DEBRIS_SIZE),
+                ..default()
+            },
+            Transform::from_translation(position.extend(1.0)),
+            Debris {
+                timer: Timer::from_seconds(DEBRIS_SECONDS, TimerMode::Once),
+                velocity: Vec2::from_angle(angle) * speed,
+            },
+        ));
+    }
+}
+
+fn update_debris(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut debris_q: Query<(Entity, &mut Debris, &mut Transform, &mut Sprite)>,
+) {
+    for (e, mut debris, mut transform, mut sprite) in &mut debris_q {
+        debris.timer.tick(time.delta());
+        if debris.timer.finished() {
+            commands.entity(e).despawn();
+            continue;
+        }
+        debris.velocity.y -= DEBRIS_GRAVITY;
+        transform.translation += debris.velocity.extend(0.0);
+        sprite.color.set_alpha(debris.timer.fraction_remaining());
+    }
+}
+
+type NeedsTint = Or<(Changed<Breakable>, With<HitFlash>, With<Burning>)>;
+
+type Tinted<'a> = (
+    Entity,
+    &'a Breakable,
+    &'a mut Sprite,
+    Option<&'a mut HitFlash>,
+    Has<Burning>,
+);
+
+/// darken blocks the more damaged they are, flicker them while they burn and flash them
+/// when hit
+fn show_damage(mut commands: Commands, time: Res<Time>, mut breakable_q: Query<Tinted, NeedsTint>) {
+    for (e, breakable, mut sprite, flash, burning) in &mut breakable_q {
+        let mut color = Color::WHITE.mix(&DAMAGED_TINT, breakable.damage_fraction());
+        if burning {
+            let flicker = (time.elapsed_secs() * FIRE_FLICKER_SPEED).sin() * 0.2;
+            color = color.mix(&FIRE_TINT, 0.5 + flicker);
+        }
+        if let Some(mut flash) = flash {
+            flash.tick(time.delta());
+            if flash.finished() {
+                commands.entity(e).remove::<HitFlash>();
+            } else {
+                color = color.mix(&HIT_FLASH_COLOR, flash.fraction_remaining());
+            }
+        }
+        sprite.color = color;
+    }
+}
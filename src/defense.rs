@@ -0,0 +1,305 @@
+//! Structures that protect a base from incoming shots.
+//!
+//! Shield domes absorb a few enemy shots each turn, point-defense turrets try to shoot
+//! down enemy shots flying close by and deflector plates send shots back the way they
+//! came, now belonging to the deflector's owner. Extinguishers keep fires away.
+
+use bevy::{math::I64Vec2, prelude::*};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::fire::{EXTINGUISHER_RADIUS, Extinguisher};
+use crate::fixed::{self, Position};
+use crate::launcher::beam;
+use crate::rng::{GameRng, RngStream};
+use crate::{
+    Battlefield, Board, Breakable, CannonBall, Footprint, GRID_SIZE, Grid, PlayerSide, TurnStarted,
+};
+
+const SHIELD_COST: u32 = 150;
+const POINT_DEFENSE_COST: u32 = 120;
+const DEFLECTOR_COST: u32 = 60;
+const EXTINGUISHER_COST: u32 = 40;
+
+const SHIELD_HEALTH: u8 = 3;
+const POINT_DEFENSE_HEALTH: u8 = 2;
+const DEFLECTOR_HEALTH: u8 = 4;
+const EXTINGUISHER_HEALTH: u8 = 1;
+
+/// in cells from the generator's middle
+const SHIELD_RADIUS: i64 = 4;
+const SHIELD_HITS_PER_TURN: u32 = 3;
+/// in front of everything it covers
+const DOME_Z: f32 = 0.5;
+/// in cells from the turret
+const POINT_DEFENSE_RANGE: i64 = 5;
+const INTERCEPT_CHANCE: f32 = 0.4;
+/// ticks between shots, about 0.4 seconds at the default 64 ticks a second
+const POINT_DEFENSE_RELOAD_TICKS: u32 = 26;
+const TRACER_HIT_COLOR: Color = Color::srgb(1.0, 0.9, 0.3);
+const TRACER_MISS_COLOR: Color = Color::srgba(0.8, 0.8, 0.8, 0.6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum DefenseKind {
+    Shield,
+    PointDefense,
+    Deflector,
+    Extinguisher,
+}
+
+impl DefenseKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DefenseKind::Shield => "Shield dome",
+            DefenseKind::PointDefense => "Point defense",
+            DefenseKind::Deflector => "Deflector",
+            DefenseKind::Extinguisher => "Extinguisher",
+        }
+    }
+
+    pub fn image(&self, asset_server: &AssetServer) -> Handle<Image> {
+        match self {
+            DefenseKind::Shield => asset_server.load("shield_generator.png"),
+            DefenseKind::PointDefense => asset_server.load("point_defense.png"),
+            DefenseKind::Deflector => asset_server.load("deflector.png"),
+            DefenseKind::Extinguisher => asset_server.load("extinguisher.png"),
+        }
+    }
+
+    pub fn cost(&self) -> u32 {
+        match self {
+            DefenseKind::Shield => SHIELD_COST,
+            DefenseKind::PointDefense => POINT_DEFENSE_COST,
+            DefenseKind::Deflector => DEFLECTOR_COST,
+            DefenseKind::Extinguisher => EXTINGUISHER_COST,
+        }
+    }
+
+    pub fn health(&self) -> u8 {
+        match self {
+            DefenseKind::Shield => SHIELD_HEALTH,
+            DefenseKind::PointDefense => POINT_DEFENSE_HEALTH,
+            DefenseKind::Deflector => DEFLECTOR_HEALTH,
+            DefenseKind::Extinguisher => EXTINGUISHER_HEALTH,
+        }
+    }
+
+    /// grid cells taken up, as seen by the bottom player
+    pub fn footprint(&self) -> Footprint {
+        match self {
+            DefenseKind::Shield | DefenseKind::Deflector => Footprint::rect(2, 1),
+            DefenseKind::PointDefense | DefenseKind::Extinguisher => Footprint::rect(1, 1),
+        }
+    }
+
+    /// one line of stats for the shop tooltip
+    pub fn describe(&self) -> String {
+        let what = match self {
+            DefenseKind::Shield => format!(
+                "absorbs {SHIELD_HITS_PER_TURN} enemy shots a turn within {SHIELD_RADIUS} cells"
+            ),
+            DefenseKind::PointDefense => format!(
+                "{:.0}% chance to shoot down enemy shots within {POINT_DEFENSE_RANGE} cells",
+                INTERCEPT_CHANCE * 100.0
+            ),
+            DefenseKind::Deflector => "sends shots back at whoever fired them".to_string(),
+            DefenseKind::Extinguisher => format!(
+                "puts out fires within {EXTINGUISHER_RADIUS} cells at the start of each turn"
+            ),
+        };
+        let footprint = self.footprint();
+        format!(
+            "{}: {what}, {} hp, {}x{}, ${}",
+            self.name(),
+            self.health(),
+            footprint.width,
+            footprint.height,
+            self.cost()
+        )
+    }
+
+    pub fn iter() -> impl Iterator<Item = DefenseKind> {
+        [
+            DefenseKind::Shield,
+            DefenseKind::PointDefense,
+            DefenseKind::Deflector,
+            DefenseKind::Extinguisher,
+        ]
+        .into_iter()
+    }
+}
+
+/// A shield generator and how many more shots its dome can take this turn.
+#[derive(Component)]
+pub struct Shield {
+    charges: u32,
+}
+
+impl Shield {
+    /// whether the dome is up and covers `point`, both in sub-cells
+    pub fn covers(&self, center: I64Vec2, point: I64Vec2) -> bool {
+        self.charges > 0 && fixed::within(center, point, SHIELD_RADIUS)
+    }
+
+    pub fn absorb(&mut self) {
+        self.charges = self.charges.saturating_sub(1);
+    }
+}
+
+/// The drawn part of a shield.
+#[derive(Component)]
+pub struct Dome;
+
+/// A turret that shoots at enemy shots once it's reloaded.
+#[derive(Component)]
+pub struct PointDefense {
+    /// ticks until it can fire again
+    reload: u32,
+}
+
+/// Reflects shots instead of being damaged by them.
+#[derive(Component)]
+pub struct Deflector;
+
+pub fn spawn_defense(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    battlefield: &Battlefield,
+    kind: DefenseKind,
+    player_side: PlayerSide,
+    grid_position: I64Vec2,
+) -> Entity {
+    let footprint = kind.footprint().for_side(player_side);
+    let mut defense = commands.spawn((
+        Sprite {
+            image: kind.image(asset_server),
+            flip_y: player_side.flip_y(),
+            custom_size: Some(footprint.world_size()),
+            ..default()
+        },
+        Transform::from_translation(footprint.center(battlefield, grid_position).extend(0.0)),
+        GlobalTransform::default(),
+        Visibility::default(),
+        Breakable::new(kind.health()),
+        footprint.grid(grid_position),
+        Board { player_side },
+    ));
+    match kind {
+        DefenseKind::Shield => {
+            defense
+                .insert(Shield {
+                    charges: SHIELD_HITS_PER_TURN,
+                })
+                .with_children(|parent| {
+                    parent.spawn((
+                        Sprite {
+                            image: asset_server.load("shield_dome.png"),
+                            custom_size: Some(Vec2::splat(SHIELD_RADIUS as f32 * 2.0) * GRID_SIZE),
+                            ..default()
+                        },
+                        Transform::from_xyz(0.0, 0.0, DOME_Z),
+                        Dome,
+                    ));
+                });
+        }
+        DefenseKind::PointDefense => {
+            defense.insert(PointDefense {
+                reload: POINT_DEFENSE_RELOAD_TICKS,
+            });
+        }
+        DefenseKind::Deflector => {
+            defense.insert(Deflector);
+        }
+        DefenseKind::Extinguisher => {
+            defense.insert(Extinguisher);
+        }
+    }
+    defense.id()
+}
+
+/// enemy shots that fly into a charged dome are absorbed
+pub fn shield_shots(
+    mut commands: Commands,
+    mut shields: Query<(&mut Shield, &Grid, &Board)>,
+    shots: Query<(Entity, &Position, &CannonBall)>,
+) {
+    for (shot_e, position, shot) in &shots {
+        let Some((mut shield, ..)) = shields.iter_mut().find(|(shield, grid, board)| {
+            board.player_side != shot.player_side && shield.covers(fixed::center(grid), **position)
+        }) else {
+            continue;
+        };
+        shield.absorb();
+        commands.entity(shot_e).despawn();
+    }
+}
+
+/// domes come back up at the start of their owner's turn
+pub fn recharge_shields(
+    mut turns: EventReader<TurnStarted>,
+    mut shields: Query<(&mut Shield, &Board)>,
+) {
+    for turn in turns.read() {
+        for (mut shield, board) in &mut shields {
+            if board.player_side == turn.side {
+                shield.charges = SHIELD_HITS_PER_TURN;
+            }
+        }
+    }
+}
+
+/// fade the dome as it runs out of charges
+pub fn show_shield_charges(
+    shields: Query<(&Shield, &Children), Changed<Shield>>,
+    mut domes: Query<&mut Sprite, With<Dome>>,
+) {
+    for (shield, children) in &shields {
+        for child in children.iter() {
+            if let Ok(mut sprite) = domes.get_mut(child) {
+                let charge = shield.charges as f32 / SHIELD_HITS_PER_TURN as f32;
+                sprite.color.set_alpha(charge);
+            }
+        }
+    }
+}
+
+/// reloaded turrets fire at the closest enemy shot in range and sometimes hit it
+pub fn point_defense(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    mut rng: ResMut<GameRng>,
+    mut turrets: Query<(&mut PointDefense, &Grid, &Board)>,
+    shots: Query<(Entity, &Position, &CannonBall)>,
+) {
+    for (mut turret, grid, board) in &mut turrets {
+        if turret.reload > 0 {
+            turret.reload -= 1;
+            continue;
+        }
+        let position = fixed::center(grid);
+        let Some((shot_e, target)) = shots
+            .iter()
+            .filter(|(_, _, shot)| shot.player_side != board.player_side)
+            .map(|(e, target, _)| (e, **target))
+            .filter(|(_, target)| fixed::within(position, *target, POINT_DEFENSE_RANGE))
+            .min_by_key(|(_, target)| (*target - position).length_squared())
+        else {
+            continue;
+        };
+        turret.reload = POINT_DEFENSE_RELOAD_TICKS;
+        let hit = rng.stream(RngStream::Defense).r#gen::<f32>() < INTERCEPT_CHANCE;
+        if hit {
+            commands.entity(shot_e).despawn();
+        }
+        let color = if hit {
+            TRACER_HIT_COLOR
+        } else {
+            TRACER_MISS_COLOR
+        };
+        commands.spawn(beam(
+            fixed::to_world(&battlefield, position),
+            fixed::to_world(&battlefield, target),
+            color,
+        ));
+    }
+}
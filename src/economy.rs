@@ -0,0 +1,301 @@
+//! Money paid to each player at the start of their turn.
+//!
+//! On top of the flat income players earn interest on what they saved, a comeback bonus
+//! while they've taken more damage than their opponent and bounties for enemy structures
+//! they knocked down since their last turn.
+
+use bevy::prelude::*;
+
+use crate::combat;
+use crate::structures::{self, Structure};
+use crate::{Board, Breakable, GameSet, Player, PlayerSide, Purchasable, TurnStarted, shop};
+
+const TURN_INCOME: u32 = 100;
+const INTEREST_PERCENT: u32 = 10;
+const INTEREST_CAP: u32 = 50;
+const COMEBACK_PER_DAMAGE: u32 = 5;
+const COMEBACK_CAP: u32 = 100;
+const BOUNTY_PERCENT: u32 = 50;
+
+/// Income, the shop and what players do with their structures.
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TurnStarted>()
+            .add_event::<Damaged>()
+            .add_event::<StructureDestroyed>()
+            .init_resource::<Economy>()
+            .add_systems(
+                FixedUpdate,
+                (
+                    shop::place_purchase,
+                    shop::shop_shortcuts.after(shop::place_purchase),
+                    structures::open_structure_menu,
+                    structures::structure_actions,
+                    // so the click on the move button doesn't put it down too
+                    structures::place_move
+                        .after(structures::open_structure_menu)
+                        .before(structures::structure_actions),
+                )
+                    .in_set(GameSet::Commands),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    track_damage.after(combat::explode),
+                    pay_income.after(track_damage),
+                )
+                    .in_set(GameSet::Resolution),
+            );
+    }
+}
+
+/// The numbers behind everyone's income, insert a different one to change the rules.
+#[derive(Resource, Debug, Clone)]
+pub struct Economy {
+    pub base_income: u32,
+    /// share of the money a player has saved paid as interest
+    pub interest_percent: u32,
+    pub interest_cap: u32,
+    /// paid for every point of damage taken beyond the opponent's
+    pub comeback_per_damage: u32,
+    pub comeback_cap: u32,
+    /// share of a destroyed enemy structure's price paid to whoever knocked it down
+    pub bounty_percent: u32,
+}
+
+impl Default for Economy {
+    fn default() -> Self {
+        Economy {
+            base_income: TURN_INCOME,
+            interest_percent: INTEREST_PERCENT,
+            interest_cap: INTEREST_CAP,
+            comeback_per_damage: COMEBACK_PER_DAMAGE,
+            comeback_cap: COMEBACK_CAP,
+            bounty_percent: BOUNTY_PERCENT,
+        }
+    }
+}
+
+impl Economy {
+    /// what `player` gets at the start of their turn
+    pub fn income(&self, player: &Player, opponent_damage_taken: u32) -> IncomeBreakdown {
+        let interest =
+            (player.money.saturating_mul(self.interest_percent) / 100).min(self.interest_cap);
+        let comeback = player
+            .damage_taken
+            .saturating_sub(opponent_damage_taken)
+            .saturating_mul(self.comeback_per_damage)
+            .min(self.comeback_cap);
+        IncomeBreakdown {
+            base: self.base_income,
+            interest,
+            comeback,
+            bounty: player.bounty,
+        }
+    }
+
+    fn bounty(&self, kind: Purchasable) -> u32 {
+        kind.cost().saturating_mul(self.bounty_percent) / 100
+    }
+}
+
+/// Where a player's income for a turn came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IncomeBreakdown {
+    pub base: u32,
+    pub interest: u32,
+    pub comeback: u32,
+    pub bounty: u32,
+}
+
+impl IncomeBreakdown {
+    pub fn total(&self) -> u32 {
+        self.base + self.interest + self.comeback + self.bounty
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "${} base + ${} interest + ${} comeback + ${} bounty = ${}",
+            self.base,
+            self.interest,
+            self.comeback,
+            self.bounty,
+            self.total()
+        )
+    }
+}
+
+/// Something on `side` lost health.
+#[derive(Event)]
+pub struct Damaged {
+    pub side: PlayerSide,
+    pub amount: u32,
+}
+
+/// One of a player's structures was knocked down.
+#[derive(Event)]
+pub struct StructureDestroyed {
+    pub side: PlayerSide,
+    pub kind: Purchasable,
+    /// whose shot did it, `None` for weather and fire
+    pub attacker: Option<PlayerSide>,
+}
+
+/// send the events for the health something on a player's side just lost
+pub fn report_damage(
+    health_before: u8,
+    breakable: &Breakable,
+    board: &Board,
+    structure: Option<&Structure>,
+    attacker: Option<PlayerSide>,
+    damaged: &mut EventWriter<Damaged>,
+    destroyed: &mut EventWriter<StructureDestroyed>,
+) {
+    let amount = (health_before - breakable.health) as u32;
+    if amount > 0 {
+        damaged.write(Damaged {
+            side: board.player_side,
+            amount,
+        });
+    }
+    if let Some(structure) = structure.filter(|_| breakable.health == 0) {
+        destroyed.write(StructureDestroyed {
+            side: board.player_side,
+            kind: structure.item,
+            attacker,
+        });
+    }
+}
+
+/// keep count of the damage each player took and the bounties they earned, only knocking
+/// down the other player's structures pays
+pub fn track_damage(
+    economy: Res<Economy>,
+    mut damaged: EventReader<Damaged>,
+    mut destroyed: EventReader<StructureDestroyed>,
+    mut players: Query<&mut Player>,
+) {
+    for event in damaged.read() {
+        for mut player in players.iter_mut().filter(|p| p.side == event.side) {
+            player.damage_taken += event.amount;
+        }
+    }
+    for event in destroyed.read() {
+        let Some(attacker) = event.attacker.filter(|attacker| *attacker != event.side) else {
+            continue;
+        };
+        for mut player in players.iter_mut().filter(|p| p.side == attacker) {
+            player.bounty += economy.bounty(event.kind);
+        }
+    }
+}
+
+/// pay whoever's turn just started
+fn pay_income(
+    economy: Res<Economy>,
+    mut turns: EventReader<TurnStarted>,
+    mut players: Query<&mut Player>,
+) {
+    for turn in turns.read() {
+        let opponent_damage_taken = players
+            .iter()
+            .find(|p| p.side != turn.side)
+            .map_or(0, |p| p.damage_taken);
+        for mut player in players.iter_mut().filter(|p| p.side == turn.side) {
+            let income = economy.income(&player, opponent_damage_taken);
+            player.money += income.total();
+            player.bounty = 0;
+            player.last_income = income;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::PlayerState;
+    use crate::launcher::LauncherKind;
+
+    const CANNON: Purchasable = Purchasable::Launcher(LauncherKind::Cannon);
+
+    fn player(side: PlayerSide, money: u32, damage_taken: u32) -> Player {
+        Player {
+            side,
+            money,
+            actions: 0,
+            damage_taken,
+            bounty: 0,
+            last_income: IncomeBreakdown::default(),
+            state: PlayerState::WaitingForTurn,
+        }
+    }
+
+    #[test]
+    fn interest_is_capped() {
+        let economy = Economy::default();
+        let saver = player(PlayerSide::Bottom, 200, 0);
+        assert_eq!(economy.income(&saver, 0).interest, 20);
+        let rich = player(PlayerSide::Bottom, 10_000, 0);
+        assert_eq!(economy.income(&rich, 0).interest, INTEREST_CAP);
+    }
+
+    #[test]
+    fn comeback_pays_for_damage_beyond_the_opponents_and_is_capped() {
+        let economy = Economy::default();
+        let behind = player(PlayerSide::Bottom, 0, 10);
+        assert_eq!(economy.income(&behind, 6).comeback, 4 * COMEBACK_PER_DAMAGE);
+        assert_eq!(economy.income(&behind, 20).comeback, 0);
+        let wrecked = player(PlayerSide::Bottom, 0, 1_000);
+        assert_eq!(economy.income(&wrecked, 0).comeback, COMEBACK_CAP);
+    }
+
+    /// run `track_damage` on a structure of `side` knocked down by `attacker`, and return
+    /// the bounties of the bottom and top players
+    fn bounties_after(side: PlayerSide, attacker: Option<PlayerSide>) -> (u32, u32) {
+        let mut world = World::new();
+        world.init_resource::<Economy>();
+        world.init_resource::<Events<Damaged>>();
+        world.init_resource::<Events<StructureDestroyed>>();
+        let bottom = world.spawn(player(PlayerSide::Bottom, 0, 0)).id();
+        let top = world.spawn(player(PlayerSide::Top, 0, 0)).id();
+        world.send_event(StructureDestroyed {
+            side,
+            kind: CANNON,
+            attacker,
+        });
+        world.run_system_once(track_damage).unwrap();
+        let bounty = |e| world.get::<Player>(e).unwrap().bounty;
+        (bounty(bottom), bounty(top))
+    }
+
+    #[test]
+    fn knocking_down_an_enemy_structure_pays_a_bounty() {
+        let bounty = CANNON.cost() * BOUNTY_PERCENT / 100;
+        assert_eq!(
+            bounties_after(PlayerSide::Top, Some(PlayerSide::Bottom)),
+            (bounty, 0)
+        );
+        let income = Economy::default().income(
+            &Player {
+                bounty,
+                ..player(PlayerSide::Bottom, 0, 0)
+            },
+            0,
+        );
+        assert_eq!(income.bounty, bounty);
+        assert_eq!(income.total(), TURN_INCOME + bounty);
+    }
+
+    #[test]
+    fn own_goals_and_weather_pay_no_bounty() {
+        assert_eq!(
+            bounties_after(PlayerSide::Top, Some(PlayerSide::Top)),
+            (0, 0)
+        );
+        assert_eq!(bounties_after(PlayerSide::Top, None), (0, 0));
+    }
+}
@@ -0,0 +1,749 @@
+//! Level editor for map files.
+//!
+//! Left mouse paints with the selected tool, right mouse erases. Every stroke (press to
+//! release) is one undo step.
+
+use std::path::PathBuf;
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::map::{EMPTY_SYMBOL, MAP_EXTENSION, MapDefinition, MapStructure, maps_dir};
+use crate::shop::Shortcut;
+use crate::{
+    AppState, Background, Battlefield, Footprint, GRID_SIZE, MainCamera, NORMAL_BUTTON,
+    Purchasable, SingleBlockType, from_grid_coords, spawn_block, spawn_purchasable, to_grid_coords,
+};
+
+const DEFAULT_MAP_WIDTH: i32 = 20;
+const DEFAULT_MAP_HEIGHT: i32 = 40;
+
+const DIVIDER_THICKNESS: f32 = 2.0;
+const DIVIDER_COLOR: Color = Color::srgba(1.0, 1.0, 1.0, 0.6);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditorTool {
+    Block(SingleBlockType),
+    Structure(Purchasable),
+    Erase,
+}
+
+impl EditorTool {
+    fn iter() -> impl Iterator<Item = EditorTool> {
+        SingleBlockType::iter()
+            .map(EditorTool::Block)
+            .chain(
+                Purchasable::iter()
+                    .filter(|item| !matches!(item, Purchasable::Block(_)))
+                    .map(EditorTool::Structure),
+            )
+            .chain(std::iter::once(EditorTool::Erase))
+    }
+
+    fn label(&self) -> String {
+        match self {
+            EditorTool::Block(block_type) => block_type.name().to_string(),
+            EditorTool::Structure(item) => item.to_string(),
+            EditorTool::Erase => "Erase".to_string(),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            EditorTool::Block(block_type) => block_type.describe(),
+            EditorTool::Structure(item) => item.describe(),
+            EditorTool::Erase => self.label(),
+        }
+    }
+}
+
+/// The part of the map that undo/redo restores.
+#[derive(Debug, Clone, PartialEq)]
+struct EditorGrid {
+    divider: i32,
+    /// row major, bottom row first
+    cells: Vec<Option<SingleBlockType>>,
+    structures: Vec<MapStructure>,
+}
+
+#[derive(Resource)]
+pub struct EditorMap {
+    name: String,
+    path: PathBuf,
+    width: i32,
+    height: i32,
+    grid: EditorGrid,
+    undo: Vec<EditorGrid>,
+    redo: Vec<EditorGrid>,
+}
+
+impl EditorMap {
+    pub fn from_definition(map: &MapDefinition, path: PathBuf) -> EditorMap {
+        let mut cells = vec![None; (map.width * map.height) as usize];
+        for (position, block_type) in map.blocks() {
+            cells[(position.y * map.width as i64 + position.x) as usize] = Some(block_type);
+        }
+        EditorMap {
+            name: map.name.clone(),
+            path,
+            width: map.width,
+            height: map.height,
+            grid: EditorGrid {
+                divider: map.divider,
+                cells,
+                structures: map.structures.clone(),
+            },
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    /// where a new map called `name` is saved
+    pub fn path_for(name: &str) -> PathBuf {
+        maps_dir().join(name.to_lowercase().replace(' ', "_") + MAP_EXTENSION)
+    }
+
+    pub fn blank(name: String) -> EditorMap {
+        EditorMap {
+            path: EditorMap::path_for(&name),
+            name,
+            width: DEFAULT_MAP_WIDTH,
+            height: DEFAULT_MAP_HEIGHT,
+            grid: EditorGrid {
+                divider: DEFAULT_MAP_HEIGHT / 2,
+                cells: vec![None; (DEFAULT_MAP_WIDTH * DEFAULT_MAP_HEIGHT) as usize],
+                structures: Vec::new(),
+            },
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    pub fn battlefield(&self) -> Battlefield {
+        Battlefield {
+            width: self.width,
+            height: self.height,
+            divider: self.grid.divider,
+        }
+    }
+
+    fn to_definition(&self) -> MapDefinition {
+        let rows = (0..self.height)
+            .rev()
+            .map(|y| {
+                (0..self.width)
+                    .map(|x| {
+                        self.grid.cells[(y * self.width + x) as usize]
+                            .map_or(EMPTY_SYMBOL, |block_type| block_type.symbol())
+                    })
+                    .collect()
+            })
+            .collect();
+        MapDefinition {
+            name: self.name.clone(),
+            width: self.width,
+            height: self.height,
+            divider: self.grid.divider,
+            rows,
+            structures: self.grid.structures.clone(),
+        }
+    }
+
+    fn contains(&self, position: I64Vec2) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < self.width as i64
+            && position.y < self.height as i64
+    }
+
+    /// the same cell seen from the other player, the board is point symmetric
+    /// because the camera rotates 180° between turns
+    fn mirror(&self, position: I64Vec2, footprint: Footprint) -> I64Vec2 {
+        let lower_left = position - footprint.anchor;
+        I64Vec2::new(
+            self.width as i64 - lower_left.x - footprint.width,
+            self.height as i64 - lower_left.y - footprint.height,
+        ) + footprint.anchor
+    }
+
+    fn structure_at(&self, position: I64Vec2) -> Option<usize> {
+        self.grid.structures.iter().position(|structure| {
+            let anchor = I64Vec2::new(structure.position.0, structure.position.1);
+            structure
+                .kind
+                .footprint()
+                .for_side(structure.side)
+                .cells(anchor)
+                .any(|cell| cell == position)
+        })
+    }
+
+    /// Applies `tool` at `position`, returns whether anything changed.
+    fn apply(&mut self, tool: EditorTool, position: I64Vec2) -> bool {
+        let before = self.grid.clone();
+        match tool {
+            EditorTool::Block(block_type) => {
+                if !self.contains(position) {
+                    return false;
+                }
+                if let Some(index) = self.structure_at(position) {
+                    self.grid.structures.remove(index);
+                }
+                self.grid.cells[(position.y * self.width as i64 + position.x) as usize] =
+                    Some(block_type);
+            }
+            EditorTool::Structure(kind) => {
+                let side = self.battlefield().side_of(position);
+                let cells: Vec<I64Vec2> = kind.footprint().for_side(side).cells(position).collect();
+                // a structure belongs to one side, it can't reach over the divider
+                let battlefield = self.battlefield();
+                if !cells
+                    .iter()
+                    .all(|cell| self.contains(*cell) && battlefield.side_of(*cell) == side)
+                {
+                    return false;
+                }
+                for cell in cells {
+                    if let Some(index) = self.structure_at(cell) {
+                        self.grid.structures.remove(index);
+                    }
+                    self.grid.cells[(cell.y * self.width as i64 + cell.x) as usize] = None;
+                }
+                self.grid.structures.push(MapStructure {
+                    kind,
+                    side,
+                    position: (position.x, position.y),
+                });
+            }
+            EditorTool::Erase => {
+                if !self.contains(position) {
+                    return false;
+                }
+                if let Some(index) = self.structure_at(position) {
+                    self.grid.structures.remove(index);
+                }
+                self.grid.cells[(position.y * self.width as i64 + position.x) as usize] = None;
+            }
+        }
+        self.grid != before
+    }
+
+    /// refused if it would leave a structure on the other player's side
+    fn set_divider(&mut self, divider: i32) -> bool {
+        if divider <= 0 || divider >= self.height || divider == self.grid.divider {
+            return false;
+        }
+        let battlefield = Battlefield {
+            divider,
+            ..self.battlefield()
+        };
+        let crossed = self.grid.structures.iter().any(|structure| {
+            let anchor = I64Vec2::new(structure.position.0, structure.position.1);
+            structure
+                .kind
+                .footprint()
+                .for_side(structure.side)
+                .cells(anchor)
+                .any(|cell| battlefield.side_of(cell) != structure.side)
+        });
+        if crossed {
+            return false;
+        }
+        self.grid.divider = divider;
+        true
+    }
+
+    fn begin_stroke(&mut self) {
+        self.undo.push(self.grid.clone());
+    }
+
+    /// drops the undo step again if the stroke didn't change anything
+    fn end_stroke(&mut self) {
+        if self.undo.last() == Some(&self.grid) {
+            self.undo.pop();
+        } else {
+            self.redo.clear();
+        }
+    }
+
+    fn undo(&mut self) -> bool {
+        let Some(grid) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(std::mem::replace(&mut self.grid, grid));
+        true
+    }
+
+    fn redo(&mut self) -> bool {
+        let Some(grid) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(std::mem::replace(&mut self.grid, grid));
+        true
+    }
+}
+
+#[derive(Resource)]
+pub struct EditorSettings {
+    tool: EditorTool,
+    mirror: bool,
+    status: String,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        EditorSettings {
+            tool: EditorTool::Block(SingleBlockType::Dirt),
+            mirror: true,
+            status: String::new(),
+        }
+    }
+}
+
+/// Anything spawned by the editor, removed when leaving it.
+#[derive(Component)]
+pub struct EditorEntity;
+
+/// Sprites showing the map, rebuilt whenever it changes.
+#[derive(Component)]
+pub struct EditorCell;
+
+#[derive(Component)]
+pub struct EditorToolButton {
+    tool: EditorTool,
+}
+
+#[derive(Component)]
+pub struct EditorStatusText;
+
+pub fn spawn_editor_ui(mut commands: Commands) {
+    commands.insert_resource(EditorSettings::default());
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(15.0),
+                right: Val::Px(15.0),
+                width: Val::Px(220.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            EditorEntity,
+        ))
+        .with_children(|parent| {
+            for (index, tool) in EditorTool::iter().enumerate() {
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(36.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BorderColor(Color::BLACK),
+                        BackgroundColor(NORMAL_BUTTON),
+                        BorderRadius::MAX,
+                        EditorToolButton { tool },
+                    ))
+                    .with_children(|btn| {
+                        btn.spawn((
+                            Text::new(match Shortcut::for_entry(index) {
+                                Some(shortcut) => format!("{} {}", shortcut.label(), tool.label()),
+                                None => tool.label(),
+                            }),
+                            TextFont {
+                                font_size: 20.0,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                            TextShadow::default(),
+                        ));
+                    });
+            }
+
+            parent.spawn((
+                Text::new(
+                    "LMB paint, RMB erase\n\
+                     M mirror to other side\n\
+                     Up/Down move divider\n\
+                     Ctrl+Z undo, Ctrl+Y redo\n\
+                     Ctrl+S save, Esc back",
+                ),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            parent.spawn((
+                Text::default(),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                EditorStatusText,
+            ));
+        });
+}
+
+pub fn despawn_editor(
+    mut commands: Commands,
+    entities: Query<Entity, With<EditorEntity>>,
+    backgrounds: Query<Entity, With<Background>>,
+) {
+    for e in entities.iter().chain(&backgrounds) {
+        commands.entity(e).despawn();
+    }
+    commands.remove_resource::<EditorMap>();
+    commands.remove_resource::<EditorSettings>();
+}
+
+pub fn choose_tool(
+    interactions: Query<(&Interaction, &EditorToolButton), Changed<Interaction>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut settings: ResMut<EditorSettings>,
+) {
+    if let Some((_, button)) = interactions
+        .iter()
+        .find(|(i, _)| **i == Interaction::Pressed)
+    {
+        settings.tool = button.tool;
+    }
+    for (index, tool) in EditorTool::iter().enumerate() {
+        if Shortcut::for_entry(index).is_some_and(|shortcut| shortcut.just_pressed(&keys)) {
+            settings.tool = tool;
+        }
+    }
+}
+
+pub fn paint(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window: Single<&Window>,
+    camera: Single<(&Camera, &GlobalTransform), With<MainCamera>>,
+    buttons: Query<&Interaction, With<EditorToolButton>>,
+    settings: Res<EditorSettings>,
+    mut map: ResMut<EditorMap>,
+) {
+    if mouse.just_released(MouseButton::Left) || mouse.just_released(MouseButton::Right) {
+        map.bypass_change_detection().end_stroke();
+    }
+
+    // clicks on the palette shouldn't paint underneath it
+    if buttons.iter().any(|i| *i != Interaction::None) {
+        return;
+    }
+
+    let tool = if mouse.pressed(MouseButton::Left) {
+        settings.tool
+    } else if mouse.pressed(MouseButton::Right) {
+        EditorTool::Erase
+    } else {
+        return;
+    };
+    if mouse.just_pressed(MouseButton::Left) || mouse.just_pressed(MouseButton::Right) {
+        map.bypass_change_detection().begin_stroke();
+    } else if matches!(tool, EditorTool::Structure(_)) {
+        // structures are stamped once per click instead of smeared while dragging
+        return;
+    }
+
+    let (camera, camera_transform) = camera.into_inner();
+    let Some(world_position) = window
+        .cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor).ok())
+    else {
+        return;
+    };
+
+    let position = to_grid_coords(&map.battlefield(), world_position);
+    let editor = map.bypass_change_detection();
+    let mut changed = editor.apply(tool, position);
+    if settings.mirror {
+        let footprint = match tool {
+            EditorTool::Structure(kind) => kind.footprint(),
+            _ => Footprint::rect(1, 1),
+        };
+        let mirrored = editor.mirror(position, footprint);
+        changed |= editor.apply(tool, mirrored);
+    }
+    // only rebuild the sprites when a cell actually changed
+    if changed {
+        map.set_changed();
+    }
+}
+
+pub fn editor_shortcuts(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut map: ResMut<EditorMap>,
+    mut battlefield: ResMut<Battlefield>,
+    mut settings: ResMut<EditorSettings>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let ctrl = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+
+    if keys.just_pressed(KeyCode::Escape) {
+        next_state.set(AppState::MapSelect);
+    }
+    if keys.just_pressed(KeyCode::KeyM) {
+        settings.mirror = !settings.mirror;
+    }
+    if ctrl && keys.just_pressed(KeyCode::KeyZ) && map.bypass_change_detection().undo() {
+        map.set_changed();
+    }
+    if ctrl && keys.just_pressed(KeyCode::KeyY) && map.bypass_change_detection().redo() {
+        map.set_changed();
+    }
+    let divider_step = if keys.just_pressed(KeyCode::ArrowUp) {
+        1
+    } else if keys.just_pressed(KeyCode::ArrowDown) {
+        -1
+    } else {
+        0
+    };
+    if divider_step != 0 {
+        let editor = map.bypass_change_detection();
+        let divider = editor.grid.divider + divider_step;
+        editor.begin_stroke();
+        let moved = editor.set_divider(divider);
+        editor.end_stroke();
+        if moved {
+            map.set_changed();
+        }
+    }
+    if ctrl && keys.just_pressed(KeyCode::KeyS) {
+        settings.status = match map.to_definition().save(&map.path) {
+            Ok(()) => format!("Saved {}", map.path.display()),
+            Err(err) => format!("Save failed: {err}"),
+        };
+    }
+
+    // undo/redo and divider moves can change where the sides meet
+    if map.is_changed() {
+        *battlefield = map.battlefield();
+    }
+}
+
+pub fn rebuild_editor_view(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map: Res<EditorMap>,
+    cells: Query<Entity, With<EditorCell>>,
+) {
+    if !map.is_changed() {
+        return;
+    }
+    for e in &cells {
+        commands.entity(e).despawn();
+    }
+
+    let battlefield = map.battlefield();
+    for y in 0..map.height {
+        for x in 0..map.width {
+            let Some(block_type) = map.grid.cells[(y * map.width + x) as usize] else {
+                continue;
+            };
+            let position = I64Vec2::new(x as i64, y as i64);
+            let e = spawn_block(
+                &mut commands,
+                &asset_server,
+                &battlefield,
+                block_type,
+                position,
+                battlefield.side_of(position),
+            );
+            commands.entity(e).insert((EditorCell, EditorEntity));
+        }
+    }
+    for structure in &map.grid.structures {
+        let e = spawn_purchasable(
+            &mut commands,
+            &asset_server,
+            &battlefield,
+            structure.kind,
+            structure.side,
+            I64Vec2::new(structure.position.0, structure.position.1),
+        );
+        commands.entity(e).insert((EditorCell, EditorEntity));
+    }
+
+    // line between the two sides, on the boundary between divider - 1 and divider
+    let divider_y = from_grid_coords(&battlefield, I64Vec2::new(0, map.grid.divider as i64)).y
+        - GRID_SIZE.y / 2.0;
+    commands.spawn((
+        Sprite {
+            color: DIVIDER_COLOR,
+            custom_size: Some(Vec2::new(battlefield.size().x, DIVIDER_THICKNESS)),
+            ..default()
+        },
+        Transform::from_translation(Vec3::new(0.0, divider_y, 1.0)),
+        EditorCell,
+        EditorEntity,
+    ));
+}
+
+pub fn editor_status_text(
+    map: Res<EditorMap>,
+    settings: Res<EditorSettings>,
+    mut text: Single<&mut Text, With<EditorStatusText>>,
+) {
+    if !map.is_changed() && !settings.is_changed() {
+        return;
+    }
+    text.0 = format!(
+        "{}\nTool: {}\nMirror: {}\nUndo: {} Redo: {}\n{}",
+        map.name,
+        settings.tool.describe(),
+        if settings.mirror { "on" } else { "off" },
+        map.undo.len(),
+        map.redo.len(),
+        settings.status,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::PlayerSide;
+    use crate::launcher::LauncherKind;
+
+    const CANNON: EditorTool = EditorTool::Structure(Purchasable::Launcher(LauncherKind::Cannon));
+
+    fn cell(map: &EditorMap, position: I64Vec2) -> Option<SingleBlockType> {
+        map.grid.cells[(position.y * map.width as i64 + position.x) as usize]
+    }
+
+    #[test]
+    fn blocks_paint_and_erase() {
+        let mut map = EditorMap::blank("Test".to_string());
+        let position = I64Vec2::new(3, 4);
+        assert!(map.apply(EditorTool::Block(SingleBlockType::Stone), position));
+        assert_eq!(cell(&map, position), Some(SingleBlockType::Stone));
+        // painting the same block again changes nothing
+        assert!(!map.apply(EditorTool::Block(SingleBlockType::Stone), position));
+        assert!(map.apply(EditorTool::Erase, position));
+        assert_eq!(cell(&map, position), None);
+        assert!(!map.apply(EditorTool::Erase, position));
+    }
+
+    #[test]
+    fn nothing_is_painted_off_the_map() {
+        let mut map = EditorMap::blank("Test".to_string());
+        for position in [
+            I64Vec2::new(-1, 0),
+            I64Vec2::new(0, 40),
+            I64Vec2::new(20, 5),
+        ] {
+            assert!(!map.apply(EditorTool::Block(SingleBlockType::Dirt), position));
+        }
+        // the cannon is two cells wide, so it doesn't fit in the last column
+        assert!(!map.apply(CANNON, I64Vec2::new(19, 0)));
+        assert!(map.grid.structures.is_empty());
+    }
+
+    #[test]
+    fn structures_replace_what_they_cover() {
+        let mut map = EditorMap::blank("Test".to_string());
+        map.apply(EditorTool::Block(SingleBlockType::Dirt), I64Vec2::new(1, 1));
+        assert!(map.apply(CANNON, I64Vec2::new(0, 0)));
+        assert_eq!(cell(&map, I64Vec2::new(1, 1)), None);
+        assert_eq!(map.grid.structures.len(), 1);
+        assert_eq!(map.grid.structures[0].side, PlayerSide::Bottom);
+        // another one overlapping it takes its place
+        assert!(map.apply(CANNON, I64Vec2::new(1, 1)));
+        assert_eq!(map.grid.structures.len(), 1);
+        assert_eq!(map.grid.structures[0].position, (1, 1));
+        // painting a block on any of its cells removes it
+        assert!(map.apply(EditorTool::Block(SingleBlockType::Dirt), I64Vec2::new(2, 2)));
+        assert!(map.grid.structures.is_empty());
+    }
+
+    #[test]
+    fn structures_stay_on_one_side_of_the_divider() {
+        let mut map = EditorMap::blank("Test".to_string());
+        let divider = map.grid.divider as i64;
+        assert!(!map.apply(CANNON, I64Vec2::new(0, divider - 1)));
+        assert!(map.grid.structures.is_empty());
+        assert!(map.apply(CANNON, I64Vec2::new(0, divider - 2)));
+        assert!(map.apply(CANNON, I64Vec2::new(4, divider)));
+        let sides: Vec<PlayerSide> = map.grid.structures.iter().map(|s| s.side).collect();
+        assert_eq!(sides, [PlayerSide::Bottom, PlayerSide::Top]);
+    }
+
+    #[test]
+    fn the_divider_doesnt_move_past_structures() {
+        let mut map = EditorMap::blank("Test".to_string());
+        let divider = map.grid.divider;
+        // right below the divider, so moving it down would put it on the top side
+        map.apply(CANNON, I64Vec2::new(0, divider as i64 - 2));
+        assert!(!map.set_divider(divider - 1));
+        assert_eq!(map.grid.divider, divider);
+        assert!(map.set_divider(divider + 1));
+        map.apply(EditorTool::Erase, I64Vec2::new(0, divider as i64 - 2));
+        assert!(map.set_divider(divider - 2));
+        assert!(!map.set_divider(0));
+    }
+
+    #[test]
+    fn mirrored_structures_cover_the_mirrored_cells() {
+        let mut map = EditorMap::blank("Test".to_string());
+        let footprint = LauncherKind::Cannon.footprint();
+        let position = I64Vec2::new(3, 5);
+        let mirrored = map.mirror(position, footprint);
+        assert_eq!(map.mirror(mirrored, footprint), position);
+
+        map.apply(CANNON, position);
+        map.apply(CANNON, mirrored);
+        let cells = |structure: &MapStructure| -> HashSet<I64Vec2> {
+            let anchor = I64Vec2::new(structure.position.0, structure.position.1);
+            let footprint = structure.kind.footprint().for_side(structure.side);
+            footprint.cells(anchor).collect()
+        };
+        let flipped: HashSet<I64Vec2> = cells(&map.grid.structures[0])
+            .into_iter()
+            .map(|cell| I64Vec2::new(map.width as i64 - 1, map.height as i64 - 1) - cell)
+            .collect();
+        assert_eq!(map.grid.structures[1].side, PlayerSide::Top);
+        assert_eq!(cells(&map.grid.structures[1]), flipped);
+    }
+
+    #[test]
+    fn strokes_undo_and_redo_as_one_step() {
+        let mut map = EditorMap::blank("Test".to_string());
+        let blank = map.grid.clone();
+        map.begin_stroke();
+        for x in 0..3 {
+            map.apply(EditorTool::Block(SingleBlockType::Wood), I64Vec2::new(x, 0));
+        }
+        map.end_stroke();
+        let painted = map.grid.clone();
+
+        // a stroke that changes nothing isn't an undo step
+        map.begin_stroke();
+        map.apply(EditorTool::Erase, I64Vec2::new(10, 10));
+        map.end_stroke();
+        assert_eq!(map.undo.len(), 1);
+
+        assert!(map.undo());
+        assert_eq!(map.grid, blank);
+        assert!(!map.undo());
+        assert!(map.redo());
+        assert_eq!(map.grid, painted);
+        assert!(!map.redo());
+
+        // a new stroke after undoing drops what could be redone
+        map.undo();
+        map.begin_stroke();
+        map.apply(EditorTool::Erase, I64Vec2::new(0, 0));
+        map.apply(EditorTool::Block(SingleBlockType::Dirt), I64Vec2::new(0, 0));
+        map.end_stroke();
+        assert!(!map.redo());
+    }
+}
@@ -0,0 +1,124 @@
+//! Fires started by incendiary shots and blasts.
+//!
+//! Rockets, lasers and explosions set wood and grass alight. At the start of every turn
+//! each fire hurts its block and may spread to the flammable blocks next to it, unless
+//! an extinguisher is close enough to put it out.
+
+use std::collections::{HashMap, HashSet};
+
+use bevy::{math::I64Vec2, prelude::*};
+use rand::Rng;
+
+use crate::combat::{BreakableTarget, BreakableTargetItem, DamageEvents, damage_target};
+use crate::rng::{GameRng, RngStream};
+use crate::{AmmoType, Grid, TurnStarted};
+
+/// health a burning block loses at the start of every turn
+const FIRE_DAMAGE: u8 = 1;
+/// chance each turn for a fire to catch each flammable neighbour
+const SPREAD_CHANCE: f32 = 0.3;
+/// in cells either way from the extinguisher
+pub const EXTINGUISHER_RADIUS: i64 = 2;
+
+/// A block that's on fire.
+#[derive(Component)]
+pub struct Burning;
+
+/// Puts out fires around it and keeps them from spreading there.
+#[derive(Component)]
+pub struct Extinguisher;
+
+impl AmmoType {
+    /// whether it sets flammable blocks alight
+    pub fn ignites(&self) -> bool {
+        matches!(
+            self,
+            AmmoType::Rocket
+                | AmmoType::Laser
+                | AmmoType::Blast
+                | AmmoType::Lightning
+                | AmmoType::Meteor
+        )
+    }
+}
+
+/// set the target alight if `ammo` can and the target burns
+pub fn catch_fire(commands: &mut Commands, target: &BreakableTargetItem, ammo: AmmoType) {
+    let flammable = target
+        .block
+        .is_some_and(|block| block.block_type.flammable());
+    if flammable && ammo.ignites() && !target.burning && target.breakable.health > 0 {
+        commands.entity(target.entity).insert(Burning);
+    }
+}
+
+/// the cells every extinguisher protects
+fn protected_cells(extinguishers: &Query<&Grid, With<Extinguisher>>) -> HashSet<I64Vec2> {
+    extinguishers
+        .iter()
+        .flat_map(|grid| grid.positions.iter().copied())
+        .flat_map(|center| {
+            (-EXTINGUISHER_RADIUS..=EXTINGUISHER_RADIUS).flat_map(move |x| {
+                (-EXTINGUISHER_RADIUS..=EXTINGUISHER_RADIUS)
+                    .map(move |y| center + I64Vec2::new(x, y))
+            })
+        })
+        .collect()
+}
+
+/// at the start of each turn fires hurt their blocks and spread, or get put out
+pub fn burn(
+    mut commands: Commands,
+    mut turns: EventReader<TurnStarted>,
+    mut targets: Query<BreakableTarget>,
+    extinguishers: Query<&Grid, With<Extinguisher>>,
+    mut rng: ResMut<GameRng>,
+    mut events: DamageEvents,
+) {
+    for _ in turns.read() {
+        let protected = protected_cells(&extinguishers);
+        let mut fires = Vec::new();
+        for mut target in &mut targets {
+            if !target.burning || target.breakable.health == 0 {
+                continue;
+            }
+            if target.grid.positions.iter().any(|p| protected.contains(p)) {
+                commands.entity(target.entity).remove::<Burning>();
+                // so the burning tint is cleared
+                target.breakable.set_changed();
+                continue;
+            }
+            fires.extend(target.grid.positions.iter().copied());
+            damage_target(&mut commands, &mut events, &mut target, FIRE_DAMAGE, None);
+        }
+
+        let flammable: HashMap<I64Vec2, Entity> = targets
+            .iter()
+            .filter(|target| {
+                !target.burning
+                    && target.breakable.health > 0
+                    && target
+                        .block
+                        .is_some_and(|block| block.block_type.flammable())
+            })
+            .flat_map(|target| {
+                target
+                    .grid
+                    .positions
+                    .iter()
+                    .map(move |p| (*p, target.entity))
+            })
+            .filter(|(p, _)| !protected.contains(p))
+            .collect();
+        for fire in fires {
+            for direction in [I64Vec2::X, I64Vec2::NEG_X, I64Vec2::Y, I64Vec2::NEG_Y] {
+                let Some(neighbour) = flammable.get(&(fire + direction)) else {
+                    continue;
+                };
+                if rng.stream(RngStream::Fire).r#gen::<f32>() < SPREAD_CHANCE {
+                    commands.entity(*neighbour).insert(Burning);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Whole-number positions and speeds for everything the simulation moves.
+//!
+//! Shots keep their position and velocity in sub-cells, counted from the lower left
+//! corner of the map, and everything they hit is worked out from those. Angles are whole
+//! thousandths of a radian and turned into rotations without floats. Floats only come in
+//! from the mouse, rounded into the aim before anything uses it, and go out to the
+//! `Transform`s that draw things, so every machine steps a match exactly the same way.
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::{Battlefield, GRID_SIZE, Grid};
+
+/// sub-cells in one pixel of the world
+pub const PER_PIXEL: i64 = 256;
+/// sub-cells across one grid cell
+pub const CELL: i64 = GRID_SIZE.x as i64 * PER_PIXEL;
+/// rotations keep their sine and cosine multiplied by this
+const ROTATION_SCALE: i64 = 4096;
+/// angles are in thousandths of a radian
+pub const MILLIRADIANS: i64 = 1000;
+/// the sine and cosine series are summed at this scale so rounding doesn't add up
+const SERIES_SCALE: i64 = 1 << 24;
+/// terms of the sine and cosine series, plenty for angles up to a half turn
+const SERIES_TERMS: i64 = 10;
+
+/// Where something is in sub-cells, its `Transform` follows it.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, Deref, DerefMut)]
+pub struct Position(pub I64Vec2);
+
+/// A turn by a fixed angle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rotation {
+    cos: i64,
+    sin: i64,
+}
+
+impl Rotation {
+    /// counter-clockwise by `angle` thousandths of a radian, no more than a half turn either
+    /// way, from the sine and cosine series in whole numbers
+    pub fn from_milliradians(angle: i64) -> Rotation {
+        let x = angle * SERIES_SCALE / MILLIRADIANS;
+        let (mut sin, mut cos) = (x, SERIES_SCALE);
+        let (mut sin_term, mut cos_term) = (x, SERIES_SCALE);
+        for n in 1..SERIES_TERMS {
+            sin_term = -sin_term * x / SERIES_SCALE * x / SERIES_SCALE / ((2 * n) * (2 * n + 1));
+            cos_term = -cos_term * x / SERIES_SCALE * x / SERIES_SCALE / ((2 * n - 1) * (2 * n));
+            sin += sin_term;
+            cos += cos_term;
+        }
+        let rescale = |v: i64| (v * ROTATION_SCALE + SERIES_SCALE / 2).div_euclid(SERIES_SCALE);
+        Rotation {
+            cos: rescale(cos),
+            sin: rescale(sin),
+        }
+    }
+
+    /// the same turn the other way
+    pub fn inverse(&self) -> Rotation {
+        Rotation {
+            cos: self.cos,
+            sin: -self.sin,
+        }
+    }
+
+    pub fn rotate(&self, v: I64Vec2) -> I64Vec2 {
+        I64Vec2::new(
+            v.x * self.cos - v.y * self.sin,
+            v.x * self.sin + v.y * self.cos,
+        ) / ROTATION_SCALE
+    }
+}
+
+/// a speed or offset in pixels, in sub-cells
+pub fn from_pixels(v: Vec2) -> I64Vec2 {
+    (v * PER_PIXEL as f32).round().as_i64vec2()
+}
+
+/// where a point of the world is on the map
+pub fn from_world(battlefield: &Battlefield, position: Vec2) -> I64Vec2 {
+    from_pixels(position + battlefield.size() / 2.0)
+}
+
+/// where to draw something at `position`
+pub fn to_world(battlefield: &Battlefield, position: I64Vec2) -> Vec2 {
+    position.as_vec2() / PER_PIXEL as f32 - battlefield.size() / 2.0
+}
+
+/// the grid cell `position` is in
+pub fn cell_of(position: I64Vec2) -> I64Vec2 {
+    I64Vec2::new(position.x.div_euclid(CELL), position.y.div_euclid(CELL))
+}
+
+pub fn cell_center(cell: I64Vec2) -> I64Vec2 {
+    cell * CELL + I64Vec2::splat(CELL / 2)
+}
+
+/// the middle of everything `grid` covers
+pub fn center(grid: &Grid) -> I64Vec2 {
+    let sum: I64Vec2 = grid.positions.iter().map(|cell| cell_center(*cell)).sum();
+    sum / grid.positions.len().max(1) as i64
+}
+
+/// whether `a` and `b` are no more than `cells` apart
+pub fn within(a: I64Vec2, b: I64Vec2, cells: i64) -> bool {
+    (a - b).length_squared() <= (cells * CELL).pow(2)
+}
+
+/// whether `a` and `b` are less than `cells` apart
+pub fn closer_than(a: I64Vec2, b: I64Vec2, cells: i64) -> bool {
+    (a - b).length_squared() < (cells * CELL).pow(2)
+}
+
+/// `v` with `percent` of its length left
+pub fn scale(v: I64Vec2, percent: i64) -> I64Vec2 {
+    v * percent / 100
+}
+
+/// move every `Transform` to its `Position`, only for drawing
+pub fn place_transforms(
+    battlefield: Res<Battlefield>,
+    mut moved: Query<(&Position, &mut Transform), Changed<Position>>,
+) {
+    for (position, mut transform) in &mut moved {
+        let world = to_world(&battlefield, **position);
+        transform.translation.x = world.x;
+        transform.translation.y = world.y;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotations_match_the_float_ones() {
+        for angle in (-3141..=3141).step_by(7) {
+            let rotation = Rotation::from_milliradians(angle);
+            let radians = angle as f64 / MILLIRADIANS as f64;
+            let scale = ROTATION_SCALE as f64;
+            assert!((rotation.cos as f64 - radians.cos() * scale).abs() <= 1.0);
+            assert!((rotation.sin as f64 - radians.sin() * scale).abs() <= 1.0);
+        }
+    }
+
+    #[test]
+    fn quarter_turn_points_up() {
+        let up = Rotation::from_milliradians(1571).rotate(I64Vec2::new(CELL, 0));
+        assert!(up.x.abs() <= 2);
+        assert_eq!(up.y, CELL);
+    }
+}
@@ -0,0 +1,167 @@
+//! What shots do to the things of the player who fired them.
+//!
+//! By default shots fly through their owner's things for the first few cells so they
+//! can leave the base, and anything of theirs hit after that stops the shot without
+//! being damaged. Hardcore games turn self-damage on. The rules come from the command
+//! line: `--no-friendly-fire`, `--grace <cells>` and `--hardcore`.
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::fixed;
+
+/// how far shots fly before they can hit their owner's things, in cells
+const GRACE_CELLS: i64 = 3;
+
+/// How far shots fly through their owner's things.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PassThrough {
+    /// they never hit them
+    Always,
+    /// for this many cells from the muzzle
+    Cells(i64),
+}
+
+/// What happens to a shot that reaches one of its owner's things.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OwnHit {
+    /// it flies on as if nothing was there
+    Ignored,
+    /// it stops there without doing any damage
+    Absorbed,
+    /// it does damage like it would to the enemy
+    Damaged,
+}
+
+/// The friendly fire rules, insert a different one to change them.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FriendlyFire {
+    pub pass_through: PassThrough,
+    /// hardcore, shots damage their owner's things once past the pass through
+    pub self_damage: bool,
+}
+
+impl Default for FriendlyFire {
+    fn default() -> Self {
+        FriendlyFire {
+            pass_through: PassThrough::Cells(GRACE_CELLS),
+            self_damage: false,
+        }
+    }
+}
+
+impl FriendlyFire {
+    /// the default rules changed by any friendly fire options in `args`, an error if
+    /// `--grace` isn't followed by a whole number of cells
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<FriendlyFire, String> {
+        let mut rules = FriendlyFire::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--no-friendly-fire" => rules.pass_through = PassThrough::Always,
+                "--hardcore" => rules.self_damage = true,
+                "--grace" => {
+                    let cells = args.next().ok_or("--grace needs a number of cells")?;
+                    let cells = cells
+                        .parse::<u32>()
+                        .map_err(|_| format!("--grace needs a number of cells, not `{cells}`"))?;
+                    rules.pass_through = PassThrough::Cells(cells as i64);
+                }
+                _ => {}
+            }
+        }
+        Ok(rules)
+    }
+
+    /// what a shot `flown` sub-cells from its muzzle does to its owner's things
+    pub fn own_hit(&self, flown: I64Vec2) -> OwnHit {
+        match self.pass_through {
+            PassThrough::Always => return OwnHit::Ignored,
+            PassThrough::Cells(cells) if fixed::closer_than(flown, I64Vec2::ZERO, cells) => {
+                return OwnHit::Ignored;
+            }
+            PassThrough::Cells(_) => {}
+        }
+        if self.self_damage {
+            OwnHit::Damaged
+        } else {
+            OwnHit::Absorbed
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixed::CELL;
+
+    /// `cells` straight along x, in sub-cells
+    fn flown(cells: f32) -> I64Vec2 {
+        I64Vec2::new((cells * CELL as f32) as i64, 0)
+    }
+
+    fn rules(pass_through: PassThrough, self_damage: bool) -> FriendlyFire {
+        FriendlyFire {
+            pass_through,
+            self_damage,
+        }
+    }
+
+    fn args(args: &[&str]) -> Result<FriendlyFire, String> {
+        FriendlyFire::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn options_change_the_rules() {
+        assert_eq!(args(&["launch-game"]), Ok(FriendlyFire::default()));
+        assert_eq!(
+            args(&["launch-game", "--no-friendly-fire"])
+                .unwrap()
+                .pass_through,
+            PassThrough::Always
+        );
+        assert_eq!(
+            args(&["launch-game", "--grace", "5", "--hardcore"]),
+            Ok(rules(PassThrough::Cells(5), true))
+        );
+    }
+
+    #[test]
+    fn bad_grace_is_rejected() {
+        assert!(args(&["launch-game", "--grace", "five"]).is_err());
+        assert!(args(&["launch-game", "--grace", "-2"]).is_err());
+        assert!(args(&["launch-game", "--grace"]).is_err());
+    }
+
+    #[test]
+    fn shots_leave_the_base_by_default() {
+        let rules = FriendlyFire::default();
+        assert_eq!(rules.own_hit(flown(0.0)), OwnHit::Ignored);
+        assert_eq!(
+            rules.own_hit(flown(GRACE_CELLS as f32 - 0.1)),
+            OwnHit::Ignored
+        );
+        assert_eq!(rules.own_hit(flown(GRACE_CELLS as f32)), OwnHit::Absorbed);
+    }
+
+    #[test]
+    fn always_passing_through_never_hits_own_things() {
+        for self_damage in [false, true] {
+            let rules = rules(PassThrough::Always, self_damage);
+            assert_eq!(rules.own_hit(flown(0.0)), OwnHit::Ignored);
+            assert_eq!(rules.own_hit(flown(100.0)), OwnHit::Ignored);
+        }
+    }
+
+    #[test]
+    fn no_grace_hits_own_things_straight_away() {
+        let rules = rules(PassThrough::Cells(0), false);
+        assert_eq!(rules.own_hit(flown(0.0)), OwnHit::Absorbed);
+    }
+
+    #[test]
+    fn self_damage_hurts_own_things_after_the_grace() {
+        let rules = rules(PassThrough::Cells(2), true);
+        assert_eq!(rules.own_hit(flown(1.0)), OwnHit::Ignored);
+        assert_eq!(rules.own_hit(flown(2.5)), OwnHit::Damaged);
+    }
+}
@@ -0,0 +1,558 @@
+//! Plays the game headlessly for tests.
+//!
+//! `Harness` builds the game's `App` on `MinimalPlugins`, starts a match on a map given as
+//! map file text and does what a player would: select and fire launchers, buy things and
+//! end the turn. It only moves the mouse, presses keys and clicks buttons, the game's own
+//! systems do the rest. Every update runs exactly one fixed tick, so tests count ticks
+//! instead of waiting on the clock.
+
+use bevy::asset::AssetPlugin;
+use bevy::state::app::StatesPlugin;
+use bevy::time::TimeUpdateStrategy;
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::aim::{FULL_POWER_DRAG, aim_direction};
+use crate::friendly_fire::FriendlyFire;
+use crate::input::TickInput;
+use crate::launcher::Launcher;
+use crate::map::MapDefinition;
+use crate::rng::GameRng;
+use crate::structures::{StructureAction, StructureActionButton};
+use crate::weather::{Weather, WeatherSettings};
+use crate::{
+    ActivePlayer, AppState, Battlefield, Breakable, GamePlugins, Grid, Menu, Player, PlayerSide,
+    PlayerState, Purchasable, PurchaseButton, PurchaseMenuButton, SelectedMap, Turn,
+    from_grid_coords,
+};
+
+/// same every run so nothing in a test is left to chance
+const SEED: u64 = 1;
+
+pub struct Harness {
+    app: App,
+}
+
+/// How a match ended up, for comparing two runs.
+#[derive(Debug, PartialEq)]
+pub struct Snapshot {
+    standing: Vec<(I64Vec2, u8)>,
+    money: (u32, u32),
+    weather: Weather,
+}
+
+impl Harness {
+    /// a match on the map in `source` with the default rules and no weather, ready for
+    /// the bottom player's first turn
+    pub fn new(source: &str) -> Harness {
+        Harness::build(source, SEED, false)
+    }
+
+    /// the same match with weather rolled from `seed`
+    pub fn with_weather(source: &str, seed: u64) -> Harness {
+        Harness::build(source, seed, true)
+    }
+
+    fn build(source: &str, seed: u64, weather: bool) -> Harness {
+        let map = MapDefinition::parse(source).expect("test map should parse");
+        let tick = Time::<Fixed>::default().timestep();
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, AssetPlugin::default()))
+            .init_asset::<Image>()
+            .init_asset::<Font>()
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .add_plugins(GamePlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(tick))
+            .insert_resource(FriendlyFire::default())
+            .insert_resource(WeatherSettings { enabled: weather })
+            .insert_resource(GameRng::new(seed))
+            .insert_resource(map.battlefield())
+            .insert_resource(SelectedMap(map));
+        app.world_mut()
+            .resource_mut::<NextState<AppState>>()
+            .set(AppState::InGame);
+        // the clock only starts on the first update, the match on the one after
+        app.update();
+        app.update();
+        Harness { app }
+    }
+
+    /// run the simulation for `ticks` fixed ticks
+    pub fn step(&mut self, ticks: usize) {
+        for _ in 0..ticks {
+            self.app.update();
+            // what the input plugin does at the start of every frame
+            let world = self.app.world_mut();
+            world.resource_mut::<ButtonInput<KeyCode>>().clear();
+            world.resource_mut::<ButtonInput<MouseButton>>().clear();
+            // and letting go of any button that was clicked
+            let mut buttons = world.query::<&mut Interaction>();
+            for mut interaction in buttons.iter_mut(world) {
+                if *interaction == Interaction::Pressed {
+                    *interaction = Interaction::None;
+                }
+            }
+        }
+    }
+
+    /// press and let go of `key`, seen on the next tick
+    pub fn tap(&mut self, key: KeyCode) {
+        let mut keys = self.app.world_mut().resource_mut::<ButtonInput<KeyCode>>();
+        keys.press(key);
+        keys.release(key);
+    }
+
+    pub fn end_turn(&mut self) {
+        self.tap(KeyCode::KeyD);
+        self.step(1);
+    }
+
+    /// move the mouse over `point` in the world
+    pub fn point_at(&mut self, point: Vec2) {
+        let mut input = self.app.world_mut().resource_mut::<TickInput>();
+        // there's no window, the screen position only places menus
+        input.cursor = Some(point);
+        input.world_cursor = Some(point);
+    }
+
+    /// move the mouse over the middle of `cell`
+    pub fn point_at_cell(&mut self, cell: I64Vec2) {
+        let battlefield = *self.app.world().resource::<Battlefield>();
+        self.point_at(from_grid_coords(&battlefield, cell));
+    }
+
+    pub fn press(&mut self, button: MouseButton) {
+        let mut mouse = self
+            .app
+            .world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>();
+        mouse.press(button);
+    }
+
+    pub fn release(&mut self, button: MouseButton) {
+        let mut mouse = self
+            .app
+            .world_mut()
+            .resource_mut::<ButtonInput<MouseButton>>();
+        mouse.release(button);
+    }
+
+    /// press the mouse on the launcher covering `cell` and hold it
+    pub fn select(&mut self, cell: I64Vec2) {
+        self.point_at_cell(cell);
+        self.press(MouseButton::Left);
+        self.step(1);
+    }
+
+    /// drag back from the selected launcher to aim it and let go of the mouse to fire it,
+    /// it flies on the next tick
+    pub fn fire(&mut self, angle: i64, power: i64) {
+        let world = self.app.world_mut();
+        let mut launchers = world.query::<(&Launcher, &Transform)>();
+        let selected = launchers
+            .iter(world)
+            .find(|(launcher, _)| launcher.is_selected)
+            .map(|(launcher, transform)| (launcher.player_side, transform.translation.truncate()));
+        if let Some((side, position)) = selected {
+            let pull = aim_direction(side, angle) * power as f32 / 100.0 * FULL_POWER_DRAG;
+            self.point_at(position - pull);
+        }
+        self.release(MouseButton::Left);
+    }
+
+    /// press a UI button, seen on the next tick
+    pub fn click(&mut self, button: Entity) {
+        self.app
+            .world_mut()
+            .entity_mut(button)
+            .insert(Interaction::Pressed);
+    }
+
+    /// the button that opens and closes the shop
+    pub fn shop_button(&mut self) -> Entity {
+        let world = self.app.world_mut();
+        world
+            .query_filtered::<Entity, With<PurchaseMenuButton>>()
+            .single(world)
+            .expect("the shop button should be spawned")
+    }
+
+    /// the shop's entry for `item`, `None` while the shop is closed
+    pub fn shop_entry(&mut self, item: Purchasable) -> Option<Entity> {
+        let world = self.app.world_mut();
+        let mut entries = world.query::<(Entity, &PurchaseButton)>();
+        entries
+            .iter(world)
+            .find(|(_, entry)| entry.item == item)
+            .map(|(e, _)| e)
+    }
+
+    /// right click the structure covering `cell` to open its menu
+    pub fn open_structure_menu(&mut self, cell: I64Vec2) {
+        self.point_at_cell(cell);
+        self.press(MouseButton::Right);
+        self.step(1);
+        self.release(MouseButton::Right);
+    }
+
+    /// the open structure menu's button for `action`, `None` without one
+    pub fn structure_button(&mut self, action: StructureAction) -> Option<Entity> {
+        let world = self.app.world_mut();
+        let mut buttons = world.query::<(Entity, &StructureActionButton)>();
+        buttons
+            .iter(world)
+            .find(|(_, button)| button.action == action)
+            .map(|(e, _)| e)
+    }
+
+    pub fn shop_open(&mut self) -> bool {
+        let world = self.app.world_mut();
+        world.query_filtered::<(), With<Menu>>().iter(world).count() > 0
+    }
+
+    /// pick `item` in the shop and click on `cell` to put it there, it stays picked if it
+    /// doesn't fit
+    pub fn purchase(&mut self, item: Purchasable, cell: I64Vec2) {
+        if !self.shop_open() {
+            let button = self.shop_button();
+            self.click(button);
+            self.step(1);
+        }
+        let entry = self.shop_entry(item).expect("the shop should list it");
+        self.click(entry);
+        self.step(1);
+        self.point_at_cell(cell);
+        self.press(MouseButton::Left);
+        self.release(MouseButton::Left);
+        self.step(1);
+    }
+
+    pub fn money(&mut self, side: PlayerSide) -> u32 {
+        let world = self.app.world_mut();
+        let mut players = world.query::<&Player>();
+        players
+            .iter(world)
+            .find(|p| p.side == side)
+            .expect("both players should be spawned")
+            .money
+    }
+
+    pub fn state(&mut self, side: PlayerSide) -> PlayerState {
+        let world = self.app.world_mut();
+        let mut players = world.query::<&Player>();
+        players
+            .iter(world)
+            .find(|p| p.side == side)
+            .expect("both players should be spawned")
+            .state
+    }
+
+    /// whose turn `ActivePlayer` says it is
+    pub fn active_side(&mut self) -> Option<PlayerSide> {
+        let active = **self.app.world().resource::<ActivePlayer>();
+        self.app.world().get::<Player>(active).map(|p| p.side)
+    }
+
+    /// the turn number and whose turn it is
+    pub fn turn(&mut self) -> (u32, PlayerSide) {
+        let world = self.app.world_mut();
+        let turn = world.query::<&Turn>().single(world).expect("one turn");
+        (turn.number, turn.player_side)
+    }
+
+    /// everything that could have turned out differently: what's left standing, the
+    /// money and the weather
+    pub fn snapshot(&mut self) -> Snapshot {
+        let world = self.app.world_mut();
+        let mut standing: Vec<(I64Vec2, u8)> = world
+            .query::<(&Breakable, &Grid)>()
+            .iter(world)
+            .map(|(breakable, grid)| (grid.positions[0], breakable.health))
+            .collect();
+        standing.sort_by_key(|(cell, _)| (cell.x, cell.y));
+        Snapshot {
+            standing,
+            money: (self.money(PlayerSide::Bottom), self.money(PlayerSide::Top)),
+            weather: *self.app.world().resource::<Weather>(),
+        }
+    }
+
+    /// health left in whatever covers `cell`, `None` if nothing's there
+    pub fn health_at(&mut self, cell: I64Vec2) -> Option<u8> {
+        let world = self.app.world_mut();
+        let mut breakables = world.query::<(&Breakable, &Grid)>();
+        breakables
+            .iter(world)
+            .find(|(_, grid)| grid.positions.contains(&cell))
+            .map(|(breakable, _)| breakable.health)
+    }
+}
+
+mod tests {
+    use super::*;
+    use crate::launcher::LauncherKind;
+    use crate::{STARTING_MONEY, SingleBlockType};
+
+    /// a cannon on the bottom player's ground with two stones in front of it on the top
+    /// player's half
+    const MAP: &str = r#"(
+        name: "Test",
+        width: 6,
+        height: 16,
+        divider: 8,
+        rows: [
+            "dddddd",
+            "dddddd",
+            "......",
+            "......",
+            "..s...",
+            "..s...",
+            "......",
+            "......",
+            "......",
+            "......",
+            "......",
+            "......",
+            "......",
+            "......",
+            "dddddd",
+            "dddddd",
+        ],
+        structures: [
+            (kind: Launcher(Cannon), side: Bottom, position: (2, 2)),
+        ],
+    )"#;
+    const CANNON: I64Vec2 = I64Vec2::new(2, 2);
+    const FRONT_STONE: I64Vec2 = I64Vec2::new(2, 10);
+    const BACK_STONE: I64Vec2 = I64Vec2::new(2, 11);
+    /// long enough for a shot to cross the map
+    const FLIGHT_TICKS: usize = 60;
+
+    #[test]
+    fn match_starts_on_the_bottom_players_turn() {
+        let mut game = Harness::new(MAP);
+        assert_eq!(game.turn(), (1, PlayerSide::Bottom));
+        assert_eq!(game.money(PlayerSide::Bottom), STARTING_MONEY);
+        assert_eq!(game.money(PlayerSide::Top), STARTING_MONEY);
+        assert_eq!(game.health_at(FRONT_STONE), Some(4));
+    }
+
+    #[test]
+    fn ending_the_turn_hands_it_over_and_pays_income() {
+        let mut game = Harness::new(MAP);
+        game.end_turn();
+        assert_eq!(game.turn(), (2, PlayerSide::Top));
+        assert!(game.money(PlayerSide::Top) > STARTING_MONEY);
+        assert_eq!(game.money(PlayerSide::Bottom), STARTING_MONEY);
+
+        game.end_turn();
+        assert_eq!(game.turn(), (3, PlayerSide::Bottom));
+        assert!(game.money(PlayerSide::Bottom) > STARTING_MONEY);
+    }
+
+    #[test]
+    fn cannonball_stops_at_the_first_stone() {
+        let mut game = Harness::new(MAP);
+        game.select(CANNON);
+        game.fire(0, 100);
+        game.step(FLIGHT_TICKS);
+
+        assert_eq!(
+            game.money(PlayerSide::Bottom),
+            STARTING_MONEY - LauncherKind::Cannon.shot_cost()
+        );
+        assert_eq!(game.health_at(FRONT_STONE), Some(3));
+        assert_eq!(game.health_at(BACK_STONE), Some(4));
+    }
+
+    #[test]
+    fn cannon_fires_again_the_same_turn() {
+        let mut game = Harness::new(MAP);
+        game.select(CANNON);
+        game.fire(0, 100);
+        game.step(FLIGHT_TICKS);
+        game.select(CANNON);
+        game.fire(0, 100);
+        game.step(FLIGHT_TICKS);
+        assert_eq!(game.health_at(FRONT_STONE), Some(2));
+    }
+
+    #[test]
+    fn buying_spends_money_and_places_the_item() {
+        let mut game = Harness::new(MAP);
+        let dirt = Purchasable::Block(SingleBlockType::Dirt);
+        let cell = I64Vec2::new(0, 2);
+        game.purchase(dirt, cell);
+        assert_eq!(game.money(PlayerSide::Bottom), STARTING_MONEY - dirt.cost());
+        assert!(game.health_at(cell).is_some());
+        assert_eq!(
+            game.state(PlayerSide::Bottom),
+            PlayerState::WaitingForAction
+        );
+
+        // the cell is taken now, so it's still waiting to be put down
+        game.purchase(dirt, cell);
+        assert_eq!(game.money(PlayerSide::Bottom), STARTING_MONEY - dirt.cost());
+        assert_eq!(
+            game.state(PlayerSide::Bottom),
+            PlayerState::Placing { item: dirt }
+        );
+    }
+
+    #[test]
+    fn shop_button_opens_and_closes_the_shop() {
+        let mut game = Harness::new(MAP);
+        let button = game.shop_button();
+        game.click(button);
+        game.step(1);
+        assert_eq!(game.state(PlayerSide::Bottom), PlayerState::PurchaseMenu);
+        assert!(game.shop_open());
+
+        game.click(button);
+        game.step(1);
+        assert_eq!(
+            game.state(PlayerSide::Bottom),
+            PlayerState::WaitingForAction
+        );
+        assert!(!game.shop_open());
+    }
+
+    #[test]
+    fn buying_from_the_shop_closes_it_and_starts_placing() {
+        let mut game = Harness::new(MAP);
+        let dirt = Purchasable::Block(SingleBlockType::Dirt);
+        let button = game.shop_button();
+        game.click(button);
+        game.step(1);
+        let entry = game.shop_entry(dirt).expect("the shop should list dirt");
+        game.click(entry);
+        game.step(1);
+        assert_eq!(
+            game.state(PlayerSide::Bottom),
+            PlayerState::Placing { item: dirt }
+        );
+        assert!(!game.shop_open());
+        // nothing is paid until it's put down
+        assert_eq!(game.money(PlayerSide::Bottom), STARTING_MONEY);
+    }
+
+    #[test]
+    fn ending_the_turn_closes_the_shop_and_hands_over_the_active_player() {
+        let mut game = Harness::new(MAP);
+        assert_eq!(game.active_side(), Some(PlayerSide::Bottom));
+        let button = game.shop_button();
+        game.click(button);
+        game.step(1);
+        game.end_turn();
+        assert!(!game.shop_open());
+        assert_eq!(game.active_side(), Some(PlayerSide::Top));
+        assert_eq!(game.state(PlayerSide::Bottom), PlayerState::WaitingForTurn);
+
+        // the top player can shop on their turn
+        game.click(button);
+        game.step(1);
+        assert_eq!(game.state(PlayerSide::Top), PlayerState::PurchaseMenu);
+    }
+
+    #[test]
+    fn selling_refunds_the_owner_and_removes_the_structure() {
+        let mut game = Harness::new(MAP);
+        game.open_structure_menu(CANNON);
+        let sell = game
+            .structure_button(StructureAction::Sell)
+            .expect("the cannon's menu should offer to sell it");
+        game.click(sell);
+        game.step(1);
+        assert!(game.money(PlayerSide::Bottom) > STARTING_MONEY);
+        assert_eq!(game.health_at(CANNON), None);
+    }
+
+    #[test]
+    fn ending_the_turn_closes_the_structure_menu() {
+        let mut game = Harness::new(MAP);
+        game.open_structure_menu(CANNON);
+        assert!(game.structure_button(StructureAction::Sell).is_some());
+        game.end_turn();
+        // the top player has nothing left to sell the bottom player's cannon with
+        assert_eq!(game.structure_button(StructureAction::Sell), None);
+        let top_money = game.money(PlayerSide::Top);
+        game.open_structure_menu(CANNON);
+        assert_eq!(game.structure_button(StructureAction::Sell), None);
+        assert_eq!(game.money(PlayerSide::Top), top_money);
+        assert_eq!(game.money(PlayerSide::Bottom), STARTING_MONEY);
+        assert!(game.health_at(CANNON).is_some());
+    }
+
+    /// the top player's TNT right across the divider from a stone of the bottom player,
+    /// clear of the cannon's line of fire
+    const BLAST_MAP: &str = r#"(
+        name: "Test",
+        width: 6,
+        height: 16,
+        divider: 8,
+        rows: [
+            "dddddd",
+            "dddddd",
+            "......",
+            "......",
+            "......",
+            "......",
+            "......",
+            "..t...",
+            "s.....",
+            "......",
+            "......",
+            "......",
+            "......",
+            "......",
+            "dddddd",
+            "dddddd",
+        ],
+        structures: [
+            (kind: Launcher(Cannon), side: Bottom, position: (2, 2)),
+        ],
+    )"#;
+    const TNT: I64Vec2 = I64Vec2::new(2, 8);
+    const OWN_STONE: I64Vec2 = I64Vec2::new(0, 7);
+
+    /// set off the TNT with the cannon under `rules`, and return what's left of the
+    /// bottom player's stone
+    fn own_stone_after_blast(rules: &[&str]) -> Option<u8> {
+        let mut game = Harness::new(BLAST_MAP);
+        let rules = FriendlyFire::from_args(rules.iter().map(|arg| arg.to_string())).unwrap();
+        game.app.world_mut().insert_resource(rules);
+        game.select(CANNON);
+        game.fire(0, 100);
+        game.step(FLIGHT_TICKS);
+        assert_eq!(game.health_at(TNT), None);
+        game.health_at(OWN_STONE)
+    }
+
+    #[test]
+    fn friendly_blasts_do_nothing_without_friendly_fire() {
+        assert_eq!(own_stone_after_blast(&["--no-friendly-fire"]), Some(4));
+        assert_eq!(own_stone_after_blast(&[]), Some(4));
+        assert!(own_stone_after_blast(&["--hardcore"]) < Some(4));
+    }
+
+    #[test]
+    fn clicking_with_no_players_left_is_ignored() {
+        let mut game = Harness::new(MAP);
+        game.select(CANNON);
+        let world = game.app.world_mut();
+        let players: Vec<Entity> = world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(world)
+            .collect();
+        for player in players {
+            world.despawn(player);
+        }
+        game.fire(0, 100);
+        game.step(1);
+        let button = game.shop_button();
+        game.click(button);
+        game.step(1);
+        assert!(!game.shop_open());
+        assert_eq!(game.health_at(FRONT_STONE), Some(4));
+    }
+}
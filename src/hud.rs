@@ -0,0 +1,207 @@
+//! Both players' status, whose turn it is and a log of earlier turns.
+
+use bevy::prelude::*;
+
+use crate::economy::StructureDestroyed;
+use crate::launcher::{LaserShot, Launcher};
+use crate::structures::Structure;
+use crate::turn::MatchInfo;
+use crate::weather::{Meteor, Weather};
+use crate::{Board, Breakable, CannonBall, Player, PlayerSide, Turn};
+
+/// how many turns the history log shows, the current one included
+const HISTORY_LINES: usize = 6;
+const HUD_FONT_SIZE: f32 = 16.0;
+
+/// What a player did on one turn.
+#[derive(Debug, Clone)]
+pub struct TurnRecord {
+    number: u32,
+    side: PlayerSide,
+    shots: u32,
+    built: u32,
+    /// enemy structures knocked down
+    destroyed: u32,
+    money_start: u32,
+    money_end: u32,
+}
+
+impl TurnRecord {
+    fn summary(&self) -> String {
+        format!(
+            "Turn {} {}: {} shots, {} built, {} destroyed, ${} -> ${}",
+            self.number,
+            self.side.name(),
+            self.shots,
+            self.built,
+            self.destroyed,
+            self.money_start,
+            self.money_end
+        )
+    }
+}
+
+/// Every turn so far, the last one is still being played.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct TurnHistory(Vec<TurnRecord>);
+
+/// Which line of the HUD a text shows.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HudText {
+    /// the map and seed, to play the match again
+    Match,
+    Turn,
+    Weather,
+    /// where the money for the current turn came from
+    Income,
+    Player(PlayerSide),
+    History,
+}
+
+pub fn spawn_hud(mut commands: Commands) {
+    commands.insert_resource(TurnHistory::default());
+    commands
+        .spawn(Node {
+            position_type: PositionType::Absolute,
+            top: Val::Px(15.0),
+            left: Val::Px(15.0),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        })
+        .with_children(|parent| {
+            for hud in [
+                HudText::Match,
+                HudText::Turn,
+                HudText::Weather,
+                HudText::Income,
+                HudText::Player(PlayerSide::Bottom),
+                HudText::Player(PlayerSide::Top),
+                HudText::History,
+            ] {
+                parent.spawn((
+                    Text::default(),
+                    TextFont {
+                        font_size: HUD_FONT_SIZE,
+                        ..default()
+                    },
+                    TextColor(Color::WHITE),
+                    TextShadow::default(),
+                    hud,
+                ));
+            }
+        });
+}
+
+/// start a record when the turn changes and count what the player does during it
+pub fn record_turn_history(
+    mut history: ResMut<TurnHistory>,
+    turn: Single<&Turn>,
+    players: Query<&Player>,
+    shots: Query<&CannonBall, (Added<CannonBall>, Without<Meteor>)>,
+    lasers: Query<&LaserShot, Added<LaserShot>>,
+    built: Query<&Board, Added<Structure>>,
+    mut destroyed: EventReader<StructureDestroyed>,
+) {
+    let money = players
+        .iter()
+        .find(|p| p.side == turn.player_side)
+        .map_or(0, |p| p.money);
+    let Some(record) = history
+        .last_mut()
+        .filter(|record| record.number == turn.number)
+    else {
+        // anything already on the field when the turn starts isn't part of it
+        history.push(TurnRecord {
+            number: turn.number,
+            side: turn.player_side,
+            shots: 0,
+            built: 0,
+            destroyed: 0,
+            money_start: money,
+            money_end: money,
+        });
+        return;
+    };
+    record.shots += shots
+        .iter()
+        .map(|shot| shot.player_side)
+        .chain(lasers.iter().map(|laser| laser.player_side))
+        .filter(|side| *side == record.side)
+        .count() as u32;
+    record.built += built
+        .iter()
+        .filter(|board| board.player_side == record.side)
+        .count() as u32;
+    record.destroyed += destroyed
+        .read()
+        .filter(|event| event.side != record.side && event.attacker == Some(record.side))
+        .count() as u32;
+    record.money_end = money;
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_hud(
+    turn: Single<&Turn>,
+    players: Query<&Player>,
+    launchers: Query<&Launcher>,
+    structures: Query<(&Board, &Breakable), With<Structure>>,
+    history: Res<TurnHistory>,
+    weather: Res<Weather>,
+    match_info: Res<MatchInfo>,
+    mut texts: Query<(&HudText, &mut Text)>,
+) {
+    for (hud, mut text) in &mut texts {
+        let line = match hud {
+            HudText::Match => format!("{}, seed {}", match_info.map, match_info.seed),
+            HudText::Turn => format!(
+                "Turn {}: {} to play (D ends the turn)",
+                turn.number,
+                turn.player_side.name()
+            ),
+            HudText::Weather => format!("Weather: {}", weather.describe(turn.player_side)),
+            HudText::Income => {
+                let Some(player) = players.iter().find(|p| p.side == turn.player_side) else {
+                    continue;
+                };
+                format!("Income: {}", player.last_income.summary())
+            }
+            HudText::Player(side) => {
+                let Some(player) = players.iter().find(|p| p.side == *side) else {
+                    continue;
+                };
+                let launcher_count = launchers.iter().filter(|l| l.player_side == *side).count();
+                let (health, max_health) = structures
+                    .iter()
+                    .filter(|(board, _)| board.player_side == *side)
+                    .fold((0, 0), |(health, max), (_, breakable)| {
+                        (
+                            health + breakable.health as u32,
+                            max + breakable.max_health as u32,
+                        )
+                    });
+                format!(
+                    "{}: ${} (+{} last turn), {} launchers, base {}/{}",
+                    side.name(),
+                    player.money,
+                    player.last_income.total(),
+                    launcher_count,
+                    health,
+                    max_health
+                )
+            }
+            HudText::History => {
+                let skip = history.len().saturating_sub(HISTORY_LINES);
+                history
+                    .iter()
+                    .skip(skip)
+                    .map(TurnRecord::summary)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+        };
+        if text.0 != line {
+            text.0 = line;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! Mouse and keyboard input for the fixed ticks.
+//!
+//! Frames and fixed ticks don't line up, so a press read straight from `ButtonInput` in a
+//! fixed tick can be missed when no tick runs that frame or seen twice when two do. Input
+//! is gathered into `TickInput` every frame in `PreUpdate` instead and cleared once a tick
+//! has used it, so every press is seen by exactly one tick. UI buttons pressed during a
+//! frame are buffered the same way.
+
+use std::hash::Hash;
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::{Battlefield, MainCamera, to_grid_coords};
+
+/// The input since the last fixed tick.
+#[derive(Resource, Default)]
+pub struct TickInput {
+    pub keys: ButtonInput<KeyCode>,
+    pub mouse: ButtonInput<MouseButton>,
+    /// where the mouse is in the window, `None` while it's outside
+    pub cursor: Option<Vec2>,
+    /// the point of the world under the mouse
+    pub world_cursor: Option<Vec2>,
+    /// UI buttons pressed since the last tick
+    pub clicked: Vec<Entity>,
+}
+
+impl TickInput {
+    /// the grid cell under the mouse
+    pub fn cell(&self, battlefield: &Battlefield) -> Option<I64Vec2> {
+        self.world_cursor
+            .map(|position| to_grid_coords(battlefield, position))
+    }
+
+    /// the first button clicked since the last tick that's in `buttons`
+    pub fn clicked<'a, T: Component>(&self, buttons: &'a Query<&T>) -> Option<&'a T> {
+        self.clicked
+            .iter()
+            .find_map(|button| buttons.get(*button).ok())
+    }
+}
+
+/// add this frame's presses and releases to what the next tick sees
+fn gather<T: Copy + Eq + Hash + Send + Sync + 'static>(
+    frame: &ButtonInput<T>,
+    tick: &mut ButtonInput<T>,
+) {
+    for button in frame.get_just_released() {
+        // pressed again in the same frame, handled below
+        if !frame.just_pressed(*button) {
+            tick.release(*button);
+        }
+    }
+    for button in frame.get_just_pressed() {
+        tick.press(*button);
+        // tapped within the frame
+        if !frame.pressed(*button) {
+            tick.release(*button);
+        }
+    }
+}
+
+type ButtonChanged = (Changed<Interaction>, With<Button>);
+
+pub fn buffer_input(
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+    camera: Query<(&Camera, &GlobalTransform), With<MainCamera>>,
+    buttons: Query<(Entity, &Interaction), ButtonChanged>,
+    mut input: ResMut<TickInput>,
+) {
+    gather(&keys, &mut input.keys);
+    gather(&mouse, &mut input.mouse);
+    input.clicked.extend(
+        buttons
+            .iter()
+            .filter(|(_, interaction)| **interaction == Interaction::Pressed)
+            .map(|(button, _)| button),
+    );
+    // headless there's no window to read, the cursor stays wherever it was put
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    input.cursor = window.cursor_position();
+    input.world_cursor = input.cursor.and_then(|cursor| {
+        let (camera, camera_transform) = camera.single().ok()?;
+        camera.viewport_to_world_2d(camera_transform, cursor).ok()
+    });
+}
+
+/// the tick has seen the presses and releases, held buttons stay held
+pub fn clear_tick_input(mut input: ResMut<TickInput>) {
+    input.keys.clear();
+    input.mouse.clear();
+    input.clicked.clear();
+}
@@ -0,0 +1,599 @@
+//! Everything a player can shoot with.
+//!
+//! Every kind of launcher is a `Launcher` with its own footprint, price, aim limits,
+//! reload and way of flying its shots. Cannons and ballistae fire straight, mortars lob
+//! shells over walls, lasers hit straight away within their range and rockets steer
+//! towards the mouse.
+
+use bevy::{math::I64Vec2, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::aim::{self, Aim, AimLimits, aim_offset};
+use crate::combat::{BreakableTarget, DamageEvents, damage_target, hit_damage};
+use crate::defense::Shield;
+use crate::fire::catch_fire;
+use crate::fixed::{self, Position, Rotation};
+use crate::friendly_fire::{FriendlyFire, OwnHit};
+use crate::input::TickInput;
+use crate::{
+    AmmoType, Battlefield, Board, Breakable, CANNONBALL_SIZE, CannonBall, Collider, Explosion,
+    Footprint, Grid, Player, PlayerSide, Velocity,
+};
+
+const CANNON_COST: u32 = 100;
+const MORTAR_COST: u32 = 150;
+const BALLISTA_COST: u32 = 120;
+const LASER_COST: u32 = 200;
+const ROCKET_LAUNCHER_COST: u32 = 180;
+
+const CANNON_HEALTH: u8 = 4;
+const MORTAR_HEALTH: u8 = 3;
+const BALLISTA_HEALTH: u8 = 3;
+const LASER_HEALTH: u8 = 2;
+const ROCKET_LAUNCHER_HEALTH: u8 = 3;
+
+const CANNONBALL_COST: u32 = 30;
+const SHELL_COST: u32 = 50;
+const BOLT_COST: u32 = 20;
+const LASER_SHOT_COST: u32 = 40;
+const ROCKET_COST: u32 = 60;
+
+/// up to about 75° either side of straight ahead, never back into your own base
+const CANNON_AIM_LIMITS: AimLimits = AimLimits {
+    max_angle: 1300,
+    min_power: 20,
+    max_power: 100,
+};
+/// mortars fire upwards and pick their range with the power
+const MORTAR_AIM_LIMITS: AimLimits = AimLimits {
+    max_angle: 600,
+    min_power: 30,
+    max_power: 100,
+};
+/// ballistae always fire hard
+const BALLISTA_AIM_LIMITS: AimLimits = AimLimits {
+    max_angle: 1000,
+    min_power: 80,
+    max_power: 100,
+};
+/// a laser has no power to pick
+const LASER_AIM_LIMITS: AimLimits = AimLimits {
+    max_angle: 1300,
+    min_power: 100,
+    max_power: 100,
+};
+const ROCKET_AIM_LIMITS: AimLimits = AimLimits {
+    max_angle: 1300,
+    min_power: 30,
+    max_power: 100,
+};
+
+/// full power speeds, in sub-cells a tick
+const CANNONBALL_VELOCITY: i64 = 10 * fixed::PER_PIXEL;
+const BOLT_VELOCITY: i64 = 16 * fixed::PER_PIXEL;
+const SHELL_VELOCITY: i64 = 8 * fixed::PER_PIXEL;
+const ROCKET_VELOCITY: i64 = 7 * fixed::PER_PIXEL;
+/// ticks a mortar shell is in the air, so the power picks how far it goes, 1.5 seconds at
+/// the default 64 ticks a second
+const SHELL_FLIGHT_TICKS: u32 = 96;
+/// how much bigger a shell looks at the top of its arc
+const SHELL_ARC_SCALE: f32 = 1.5;
+/// in cells
+const LASER_RANGE: i64 = 15;
+/// thousandths of a radian a rocket can turn each tick
+const ROCKET_TURN_RATE: i64 = 60;
+/// ticks a rocket steers before it flies straight
+const ROCKET_FUEL_TICKS: u32 = 128;
+
+const CANNONBALL_COLOR: Color = Color::WHITE;
+const SHELL_COLOR: Color = Color::srgb(0.25, 0.25, 0.25);
+const BOLT_COLOR: Color = Color::srgb(0.55, 0.38, 0.2);
+const ROCKET_COLOR: Color = Color::srgb(1.0, 0.55, 0.1);
+const BEAM_COLOR: Color = Color::srgb(1.0, 0.2, 0.2);
+const BEAM_WIDTH: f32 = 3.0;
+const BEAM_SECONDS: f32 = 0.3;
+/// how many points along each cell of a beam are checked for something to hit
+const BEAM_STEPS_PER_CELL: i64 = 4;
+
+/// How a launcher's shots get to where they're going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flight {
+    /// straight on at `speed` times the power
+    Direct { speed: i64 },
+    /// over everything, bursting where it comes down
+    Lob { speed: i64 },
+    /// hits the first thing within `range` cells straight away
+    Hitscan { range: i64 },
+    /// turns towards the mouse while it has fuel
+    Guided { speed: i64, turn_rate: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum LauncherKind {
+    Cannon,
+    Mortar,
+    Ballista,
+    Laser,
+    Rocket,
+}
+
+impl LauncherKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LauncherKind::Cannon => "Cannon",
+            LauncherKind::Mortar => "Mortar",
+            LauncherKind::Ballista => "Ballista",
+            LauncherKind::Laser => "Laser",
+            LauncherKind::Rocket => "Rocket launcher",
+        }
+    }
+
+    pub fn image(&self, asset_server: &AssetServer) -> Handle<Image> {
+        match self {
+            LauncherKind::Cannon => asset_server.load("cannon.png"),
+            LauncherKind::Mortar => asset_server.load("mortar.png"),
+            LauncherKind::Ballista => asset_server.load("ballista.png"),
+            LauncherKind::Laser => asset_server.load("laser.png"),
+            LauncherKind::Rocket => asset_server.load("rocket.png"),
+        }
+    }
+
+    pub fn cost(&self) -> u32 {
+        match self {
+            LauncherKind::Cannon => CANNON_COST,
+            LauncherKind::Mortar => MORTAR_COST,
+            LauncherKind::Ballista => BALLISTA_COST,
+            LauncherKind::Laser => LASER_COST,
+            LauncherKind::Rocket => ROCKET_LAUNCHER_COST,
+        }
+    }
+
+    pub fn health(&self) -> u8 {
+        match self {
+            LauncherKind::Cannon => CANNON_HEALTH,
+            LauncherKind::Mortar => MORTAR_HEALTH,
+            LauncherKind::Ballista => BALLISTA_HEALTH,
+            LauncherKind::Laser => LASER_HEALTH,
+            LauncherKind::Rocket => ROCKET_LAUNCHER_HEALTH,
+        }
+    }
+
+    /// grid cells taken up, as seen by the bottom player
+    pub fn footprint(&self) -> Footprint {
+        match self {
+            // the barrel on top is the weak spot
+            LauncherKind::Cannon => Footprint::rect(2, 2).with_weak_points(0b1100),
+            LauncherKind::Mortar | LauncherKind::Rocket => Footprint::rect(2, 2),
+            // the bow string in the middle
+            LauncherKind::Ballista => Footprint::rect(3, 1).with_weak_points(0b010),
+            // the lens on top
+            LauncherKind::Laser => Footprint::rect(1, 2).with_weak_points(0b10),
+        }
+    }
+
+    pub fn aim_limits(&self) -> AimLimits {
+        match self {
+            LauncherKind::Cannon => CANNON_AIM_LIMITS,
+            LauncherKind::Mortar => MORTAR_AIM_LIMITS,
+            LauncherKind::Ballista => BALLISTA_AIM_LIMITS,
+            LauncherKind::Laser => LASER_AIM_LIMITS,
+            LauncherKind::Rocket => ROCKET_AIM_LIMITS,
+        }
+    }
+
+    /// price of each shot
+    pub fn shot_cost(&self) -> u32 {
+        match self {
+            LauncherKind::Cannon => CANNONBALL_COST,
+            LauncherKind::Mortar => SHELL_COST,
+            LauncherKind::Ballista => BOLT_COST,
+            LauncherKind::Laser => LASER_SHOT_COST,
+            LauncherKind::Rocket => ROCKET_COST,
+        }
+    }
+
+    /// turns that have to start before it fires again, 0 fires as often as you can pay
+    pub fn reload_turns(&self) -> u32 {
+        match self {
+            LauncherKind::Cannon | LauncherKind::Ballista => 0,
+            LauncherKind::Mortar | LauncherKind::Rocket => 1,
+            LauncherKind::Laser => 2,
+        }
+    }
+
+    pub fn flight(&self) -> Flight {
+        match self {
+            LauncherKind::Cannon => Flight::Direct {
+                speed: CANNONBALL_VELOCITY,
+            },
+            LauncherKind::Mortar => Flight::Lob {
+                speed: SHELL_VELOCITY,
+            },
+            LauncherKind::Ballista => Flight::Direct {
+                speed: BOLT_VELOCITY,
+            },
+            LauncherKind::Laser => Flight::Hitscan { range: LASER_RANGE },
+            LauncherKind::Rocket => Flight::Guided {
+                speed: ROCKET_VELOCITY,
+                turn_rate: ROCKET_TURN_RATE,
+            },
+        }
+    }
+
+    /// what its shots count as when they hit something
+    pub fn ammo(&self) -> AmmoType {
+        match self {
+            LauncherKind::Cannon | LauncherKind::Mortar => AmmoType::Cannonball,
+            LauncherKind::Laser => AmmoType::Laser,
+            LauncherKind::Ballista => AmmoType::Bolt,
+            LauncherKind::Rocket => AmmoType::Rocket,
+        }
+    }
+
+    fn shot_color(&self) -> Color {
+        match self {
+            LauncherKind::Cannon => CANNONBALL_COLOR,
+            LauncherKind::Mortar => SHELL_COLOR,
+            LauncherKind::Ballista => BOLT_COLOR,
+            LauncherKind::Laser => BEAM_COLOR,
+            LauncherKind::Rocket => ROCKET_COLOR,
+        }
+    }
+
+    /// one line of stats for the shop tooltip
+    pub fn describe(&self) -> String {
+        let shots = match self {
+            LauncherKind::Cannon => "fires cannonballs",
+            LauncherKind::Mortar => "lobs shells over walls that burst where they land",
+            LauncherKind::Ballista => "fires fast, flat bolts",
+            LauncherKind::Laser => "hits the first thing in range instantly",
+            LauncherKind::Rocket => "fires rockets that steer towards the mouse",
+        };
+        let reload = match self.reload_turns() {
+            0 => String::new(),
+            1 => ", once a turn".to_string(),
+            turns => format!(", once every {turns} turns"),
+        };
+        let footprint = self.footprint();
+        format!(
+            "{}: {shots} for ${} a shot{reload}, {} hp, {}x{}, ${}",
+            self.name(),
+            self.shot_cost(),
+            self.health(),
+            footprint.width,
+            footprint.height,
+            self.cost()
+        )
+    }
+
+    pub fn iter() -> impl Iterator<Item = LauncherKind> {
+        [
+            LauncherKind::Cannon,
+            LauncherKind::Mortar,
+            LauncherKind::Ballista,
+            LauncherKind::Laser,
+            LauncherKind::Rocket,
+        ]
+        .into_iter()
+    }
+}
+
+/// Something that shoots, of any kind.
+#[derive(Component)]
+pub struct Launcher {
+    pub kind: LauncherKind,
+    pub player_side: PlayerSide,
+    pub is_selected: bool,
+    pub aim: Aim,
+    /// turns that have to start before it fires again
+    pub reload: u32,
+}
+
+/// A mortar shell, flying over everything until it's been up for its whole flight.
+#[derive(Component)]
+pub struct Lob {
+    /// ticks in the air so far
+    flown: u32,
+}
+
+/// A rocket that's still steering.
+#[derive(Component)]
+pub struct Guided {
+    turn: Rotation,
+    /// ticks left
+    fuel: u32,
+}
+
+/// A laser shot, resolved on the tick after it's fired and then shown as a fading beam.
+#[derive(Component)]
+pub struct LaserShot {
+    pub player_side: PlayerSide,
+    /// in sub-cells
+    origin: I64Vec2,
+    /// between the points checked along the beam, in sub-cells
+    step: I64Vec2,
+    range: i64,
+}
+
+/// The drawn part of a laser shot that already hit.
+#[derive(Component, Deref, DerefMut)]
+pub struct Beam(Timer);
+
+pub fn spawn_launcher(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    battlefield: &Battlefield,
+    kind: LauncherKind,
+    player_side: PlayerSide,
+    grid_position: I64Vec2,
+) -> Entity {
+    let footprint = kind.footprint().for_side(player_side);
+    commands
+        .spawn((
+            Sprite {
+                image: kind.image(asset_server),
+                flip_y: player_side.flip_y(),
+                custom_size: Some(footprint.world_size()),
+                ..default()
+            },
+            Transform::from_translation(footprint.center(battlefield, grid_position).extend(0.0)),
+            GlobalTransform::default(),
+            Visibility::default(),
+            Launcher {
+                kind,
+                player_side,
+                is_selected: false,
+                aim: kind.aim_limits().default_aim(),
+                reload: 0,
+            },
+            Breakable::new(kind.health()),
+            footprint.grid(grid_position),
+            Board { player_side },
+        ))
+        .with_children(aim::spawn_barrel)
+        .id()
+}
+
+/// a shot at `position` in sub-cells
+pub fn projectile(battlefield: &Battlefield, position: I64Vec2, color: Color) -> impl Bundle {
+    (
+        Sprite {
+            color,
+            custom_size: Some(CANNONBALL_SIZE),
+            ..default()
+        },
+        Position(position),
+        Transform::from_translation(fixed::to_world(battlefield, position).extend(0.0)),
+        GlobalTransform::default(),
+        Visibility::default(),
+    )
+}
+
+/// shoot out of the barrel of a launcher at `position` in sub-cells if it's loaded and
+/// `player` can pay for the shot
+pub fn fire(
+    commands: &mut Commands,
+    battlefield: &Battlefield,
+    launcher: &mut Launcher,
+    position: I64Vec2,
+    player: &mut Player,
+) {
+    let kind = launcher.kind;
+    if launcher.reload > 0 || player.money < kind.shot_cost() {
+        return;
+    }
+    let player_side = launcher.player_side;
+    let Aim { angle, power } = launcher.aim;
+    let muzzle = position + aim_offset(player_side, angle, aim::MUZZLE_DISTANCE);
+    let velocity = |speed: i64| Velocity(aim_offset(player_side, angle, speed * power / 100));
+    let ball = CannonBall {
+        player_side,
+        ammo: kind.ammo(),
+        origin: muzzle,
+        last_hit: None,
+    };
+    match kind.flight() {
+        Flight::Direct { speed } => {
+            commands.spawn((
+                projectile(battlefield, muzzle, kind.shot_color()),
+                velocity(speed),
+                ball,
+                Collider,
+            ));
+        }
+        // no collider, shells only hit where they land
+        Flight::Lob { speed } => {
+            commands.spawn((
+                projectile(battlefield, muzzle, kind.shot_color()),
+                velocity(speed),
+                ball,
+                Lob { flown: 0 },
+            ));
+        }
+        Flight::Hitscan { range } => {
+            commands.spawn(LaserShot {
+                player_side,
+                origin: muzzle,
+                step: aim_offset(player_side, angle, fixed::CELL / BEAM_STEPS_PER_CELL),
+                range,
+            });
+        }
+        Flight::Guided { speed, turn_rate } => {
+            commands.spawn((
+                projectile(battlefield, muzzle, kind.shot_color()),
+                velocity(speed),
+                ball,
+                Collider,
+                Guided {
+                    turn: Rotation::from_milliradians(turn_rate),
+                    fuel: ROCKET_FUEL_TICKS,
+                },
+            ));
+        }
+    }
+    player.money -= kind.shot_cost();
+    launcher.reload = kind.reload_turns();
+}
+
+/// grow and shrink shells along their arc and burst them where they come down
+pub fn fly_shells(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    mut shells: Query<(Entity, &mut Lob, &CannonBall, &Position, &mut Transform)>,
+    mut explosions: EventWriter<Explosion>,
+) {
+    for (e, mut lob, shell, position, mut transform) in &mut shells {
+        lob.flown += 1;
+        if lob.flown >= SHELL_FLIGHT_TICKS {
+            commands.entity(e).despawn();
+            let position = fixed::cell_of(**position);
+            if battlefield.contains(position) {
+                explosions.write(Explosion {
+                    position,
+                    attacker: Some(shell.player_side),
+                    origin: shell.origin,
+                });
+            }
+            continue;
+        }
+        let fraction = lob.flown as f32 / SHELL_FLIGHT_TICKS as f32;
+        let height = (fraction * std::f32::consts::PI).sin();
+        transform.scale = Vec3::splat(1.0 + (SHELL_ARC_SCALE - 1.0) * height);
+    }
+}
+
+/// turn rockets towards the mouse until their fuel runs out
+pub fn guide_rockets(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    battlefield: Res<Battlefield>,
+    mut rockets: Query<(Entity, &mut Guided, &mut Velocity, &Position)>,
+) {
+    let target = input
+        .world_cursor
+        .map(|target| fixed::from_world(&battlefield, target));
+    for (e, mut guided, mut velocity, position) in &mut rockets {
+        if guided.fuel == 0 {
+            commands.entity(e).remove::<Guided>();
+            continue;
+        }
+        guided.fuel -= 1;
+        let Some(target) = target else {
+            continue;
+        };
+        // turn whichever way points it closest to the target, if turning helps at all
+        let wanted = target - **position;
+        **velocity = [
+            **velocity,
+            guided.turn.rotate(**velocity),
+            guided.turn.inverse().rotate(**velocity),
+        ]
+        .into_iter()
+        .max_by_key(|heading| heading.dot(wanted))
+        .unwrap_or(**velocity);
+    }
+}
+
+/// hit the first thing along each new laser shot and draw its beam, charged enemy shields
+/// stop it
+#[allow(clippy::too_many_arguments)]
+pub fn fire_lasers(
+    mut commands: Commands,
+    battlefield: Res<Battlefield>,
+    shots: Query<(Entity, &LaserShot), Without<Beam>>,
+    mut targets: Query<BreakableTarget>,
+    mut shields: Query<(&mut Shield, &Grid, &Board)>,
+    friendly_fire: Res<FriendlyFire>,
+    mut explosions: EventWriter<Explosion>,
+    mut events: DamageEvents,
+) {
+    for (e, shot) in &shots {
+        let steps = shot.range * BEAM_STEPS_PER_CELL;
+        let mut end = shot.origin + shot.step * steps;
+        for step in 0..=steps {
+            let point = shot.origin + shot.step * step;
+            let cell = fixed::cell_of(point);
+            if !battlefield.contains(cell) {
+                end = point;
+                break;
+            }
+            if let Some((mut shield, ..)) = shields.iter_mut().find(|(shield, grid, board)| {
+                board.player_side != shot.player_side && shield.covers(fixed::center(grid), point)
+            }) {
+                shield.absorb();
+                end = point;
+                break;
+            }
+            let Some(mut target) = targets
+                .iter_mut()
+                .find(|t| t.breakable.health > 0 && t.grid.positions.contains(&cell))
+            else {
+                continue;
+            };
+            let weak = target.grid.weak_points.contains(&cell);
+            let mut damage = hit_damage(&target, AmmoType::Laser, weak);
+            if target.board.player_side == shot.player_side {
+                match friendly_fire.own_hit(point - shot.origin) {
+                    OwnHit::Ignored => continue,
+                    OwnHit::Absorbed => damage = 0,
+                    OwnHit::Damaged => {}
+                }
+            }
+            let attacker = Some(shot.player_side);
+            if damage_target(&mut commands, &mut events, &mut target, damage, attacker)
+                && target
+                    .block
+                    .is_some_and(|block| block.block_type.explodes())
+            {
+                explosions.write(Explosion {
+                    position: cell,
+                    attacker,
+                    origin: shot.origin,
+                });
+            }
+            if damage > 0 {
+                catch_fire(&mut commands, &target, AmmoType::Laser);
+            }
+            end = point;
+            break;
+        }
+
+        commands.entity(e).insert(beam(
+            fixed::to_world(&battlefield, shot.origin),
+            fixed::to_world(&battlefield, end),
+            BEAM_COLOR,
+        ));
+    }
+}
+
+/// a line from `from` to `to` that fades away
+pub fn beam(from: Vec2, to: Vec2, color: Color) -> impl Bundle {
+    let line = to - from;
+    (
+        Sprite {
+            color,
+            custom_size: Some(Vec2::new(line.length(), BEAM_WIDTH)),
+            ..default()
+        },
+        Transform::from_translation((from + line / 2.0).extend(1.0))
+            .with_rotation(Quat::from_rotation_z(line.to_angle())),
+        GlobalTransform::default(),
+        Visibility::default(),
+        Beam(Timer::from_seconds(BEAM_SECONDS, TimerMode::Once)),
+    )
+}
+
+pub fn fade_beams(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut beams: Query<(Entity, &mut Beam, &mut Sprite)>,
+) {
+    for (e, mut beam, mut sprite) in &mut beams {
+        beam.tick(time.delta());
+        if beam.finished() {
+            commands.entity(e).despawn();
+            continue;
+        }
+        sprite.color.set_alpha(beam.fraction_remaining());
+    }
+}
@@ -0,0 +1,847 @@
+//! The game as plugins, one per part of it, so a binary can put together only what it
+//! needs: the game itself adds them all with `GamePlugins`, a headless simulation can
+//! leave out the camera and the UI.
+//!
+//! `TurnPlugin` is the one every other plugin builds on. It sets up the app state, the
+//! players, the dice and the input, and runs the `GameSet`s in order.
+//!
+//! The plugins don't look at the command line. A binary that wants to change the dice,
+//! the friendly fire rules or the weather inserts its own `GameRng`, `FriendlyFire` or
+//! `WeatherSettings` before adding them.
+
+mod aim;
+mod camera;
+mod combat;
+mod defense;
+mod economy;
+mod editor;
+mod fire;
+mod fixed;
+mod friendly_fire;
+#[cfg(test)]
+mod harness;
+mod hud;
+mod input;
+mod launcher;
+mod map;
+mod rng;
+mod shop;
+mod structures;
+mod terrain;
+mod turn;
+mod ui;
+mod weather;
+
+use bevy::app::PluginGroupBuilder;
+use bevy::{math::I64Vec2, prelude::*};
+use serde::{Deserialize, Serialize};
+
+pub use camera::CameraPlugin;
+pub use combat::CombatPlugin;
+pub use economy::EconomyPlugin;
+pub use friendly_fire::FriendlyFire;
+pub use rng::GameRng;
+pub use terrain::TerrainPlugin;
+pub use turn::TurnPlugin;
+pub use ui::UiPlugin;
+pub use weather::WeatherSettings;
+
+use defense::DefenseKind;
+use economy::IncomeBreakdown;
+use launcher::LauncherKind;
+use map::MapDefinition;
+use structures::Structure;
+
+const GRID_SIZE: Vec2 = Vec2::new(20.0, 20.0);
+
+const DIRT_HEALTH: u8 = 1;
+const GRASS_HEALTH: u8 = 2;
+const STONE_HEALTH: u8 = 4;
+const METAL_HEALTH: u8 = 6;
+const WOOD_HEALTH: u8 = 2;
+const SAND_HEALTH: u8 = 1;
+const BEDROCK_HEALTH: u8 = u8::MAX;
+const GLASS_HEALTH: u8 = 1;
+const TNT_HEALTH: u8 = 1;
+const BOARD_HEALTH: u8 = 2;
+
+const DIRT_COST: u32 = 10;
+const GRASS_COST: u32 = 15;
+const STONE_COST: u32 = 40;
+const METAL_COST: u32 = 80;
+const WOOD_COST: u32 = 20;
+const SAND_COST: u32 = 5;
+const GLASS_COST: u32 = 10;
+const TNT_COST: u32 = 60;
+
+const BOARD_DEBRIS_COLOR: Color = Color::srgb(0.55, 0.38, 0.2);
+
+const CANNONBALL_SIZE: Vec2 = Vec2::new(8.0, 8.0);
+
+const STARTING_MONEY: u32 = 100;
+const BOARD_COST: u32 = 50;
+
+const NORMAL_BUTTON: Color = Color::srgb(0.15, 0.15, 0.15);
+
+/// The whole game apart from the window, rendering and the inspector, so tests and other
+/// binaries can run it headlessly.
+pub struct GamePlugins;
+
+impl PluginGroup for GamePlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(TurnPlugin)
+            .add(CameraPlugin)
+            .add(TerrainPlugin)
+            .add(CombatPlugin)
+            .add(EconomyPlugin)
+            .add(UiPlugin)
+    }
+}
+
+/// The steps of the game during a match, run in this order. Input is gathered every frame,
+/// the rest happen once each fixed tick.
+#[derive(SystemSet, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSet {
+    /// reading the mouse and keyboard into `TickInput`, in `PreUpdate`
+    Input,
+    /// acting on the input: ending the turn, aiming, firing, buying and moving things
+    Commands,
+    /// everything that moves on its own, and the weather
+    Simulation,
+    /// what hit what, and the damage, fires and money that come of it
+    Resolution,
+    /// what the players see of it all
+    Presentation,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, States)]
+pub enum AppState {
+    #[default]
+    MapSelect,
+    InGame,
+    Editor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+enum SingleBlockType {
+    Dirt,
+    Grass,
+    Stone,
+    Metal,
+    Wood,
+    Sand,
+    Bedrock,
+    Glass,
+    Tnt,
+}
+
+/// What a block was hit by, blocks resist each differently.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AmmoType {
+    Cannonball,
+    /// a ballista's bolt, pointed enough to go through wood
+    Bolt,
+    Rocket,
+    Laser,
+    /// a nearby TNT block going off
+    Blast,
+    Lightning,
+    Meteor,
+}
+
+/// What a shot does after hitting something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CollisionResponse {
+    /// it's used up
+    Stop,
+    /// it bounces off the side it hit keeping `restitution` percent of its speed
+    Ricochet { restitution: i64 },
+    /// it goes on through keeping `retained` percent of its speed
+    Penetrate { retained: i64 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+enum PlayerSide {
+    Top,
+    Bottom,
+}
+
+impl PlayerSide {
+    fn name(&self) -> &'static str {
+        match self {
+            PlayerSide::Top => "Top",
+            PlayerSide::Bottom => "Bottom",
+        }
+    }
+
+    fn other(&self) -> PlayerSide {
+        match self {
+            PlayerSide::Top => PlayerSide::Bottom,
+            PlayerSide::Bottom => PlayerSide::Top,
+        }
+    }
+
+    fn flip_y(&self) -> bool {
+        match self {
+            PlayerSide::Top => true,
+            PlayerSide::Bottom => false,
+        }
+    }
+
+    /// direction loose blocks fall, towards that player's edge of the map
+    fn down(&self) -> I64Vec2 {
+        match self {
+            PlayerSide::Top => I64Vec2::Y,
+            PlayerSide::Bottom => I64Vec2::NEG_Y,
+        }
+    }
+}
+
+impl SingleBlockType {
+    fn image(&self, asset_server: &AssetServer) -> Handle<Image> {
+        match self {
+            SingleBlockType::Dirt => asset_server.load("dirt.png"),
+            SingleBlockType::Grass => asset_server.load("dirt_grass.png"),
+            SingleBlockType::Stone => asset_server.load("stone.png"),
+            SingleBlockType::Metal => asset_server.load("metal.png"),
+            SingleBlockType::Wood => asset_server.load("wood.png"),
+            SingleBlockType::Sand => asset_server.load("sand.png"),
+            SingleBlockType::Bedrock => asset_server.load("bedrock.png"),
+            SingleBlockType::Glass => asset_server.load("glass.png"),
+            SingleBlockType::Tnt => asset_server.load("tnt.png"),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SingleBlockType::Dirt => "Dirt",
+            SingleBlockType::Grass => "Grass",
+            SingleBlockType::Stone => "Stone",
+            SingleBlockType::Metal => "Metal",
+            SingleBlockType::Wood => "Wood",
+            SingleBlockType::Sand => "Sand",
+            SingleBlockType::Bedrock => "Bedrock",
+            SingleBlockType::Glass => "Glass",
+            SingleBlockType::Tnt => "TNT",
+        }
+    }
+
+    fn health(&self) -> u8 {
+        match self {
+            SingleBlockType::Dirt => DIRT_HEALTH,
+            SingleBlockType::Grass => GRASS_HEALTH,
+            SingleBlockType::Stone => STONE_HEALTH,
+            SingleBlockType::Metal => METAL_HEALTH,
+            SingleBlockType::Wood => WOOD_HEALTH,
+            SingleBlockType::Sand => SAND_HEALTH,
+            SingleBlockType::Bedrock => BEDROCK_HEALTH,
+            SingleBlockType::Glass => GLASS_HEALTH,
+            SingleBlockType::Tnt => TNT_HEALTH,
+        }
+    }
+
+    /// health lost per hit, 0 means the block shrugs it off
+    fn damage_taken(&self, ammo: AmmoType) -> u8 {
+        match (self, ammo) {
+            (SingleBlockType::Bedrock, _) => 0,
+            // stone cracks and wood splinters in a blast
+            (SingleBlockType::Stone | SingleBlockType::Wood, AmmoType::Blast) => 2,
+            (SingleBlockType::Metal, AmmoType::Blast) => 0,
+            _ => 1,
+        }
+    }
+
+    /// what a shot of `ammo` does after hitting the block
+    fn response(&self, ammo: AmmoType) -> CollisionResponse {
+        match (self, ammo) {
+            // rockets burst on whatever they touch
+            (
+                _,
+                AmmoType::Rocket
+                | AmmoType::Laser
+                | AmmoType::Blast
+                | AmmoType::Lightning
+                | AmmoType::Meteor,
+            ) => CollisionResponse::Stop,
+            (SingleBlockType::Metal | SingleBlockType::Bedrock, AmmoType::Bolt) => {
+                CollisionResponse::Ricochet { restitution: 40 }
+            }
+            (SingleBlockType::Metal, _) => CollisionResponse::Ricochet { restitution: 60 },
+            (SingleBlockType::Bedrock, _) => CollisionResponse::Ricochet { restitution: 50 },
+            (SingleBlockType::Glass, _) => CollisionResponse::Penetrate { retained: 80 },
+            (SingleBlockType::Dirt | SingleBlockType::Sand | SingleBlockType::Grass, _) => {
+                CollisionResponse::Penetrate { retained: 50 }
+            }
+            (SingleBlockType::Wood, AmmoType::Bolt) => {
+                CollisionResponse::Penetrate { retained: 70 }
+            }
+            (SingleBlockType::Stone | SingleBlockType::Wood | SingleBlockType::Tnt, _) => {
+                CollisionResponse::Stop
+            }
+        }
+    }
+
+    /// colour of the bits that fly off when it breaks
+    fn debris_color(&self) -> Color {
+        match self {
+            SingleBlockType::Dirt => Color::srgb(0.45, 0.3, 0.18),
+            SingleBlockType::Grass => Color::srgb(0.3, 0.6, 0.2),
+            SingleBlockType::Stone => Color::srgb(0.5, 0.5, 0.52),
+            SingleBlockType::Metal => Color::srgb(0.7, 0.75, 0.8),
+            SingleBlockType::Wood => BOARD_DEBRIS_COLOR,
+            SingleBlockType::Sand => Color::srgb(0.87, 0.77, 0.5),
+            SingleBlockType::Bedrock => Color::srgb(0.2, 0.2, 0.2),
+            SingleBlockType::Glass => Color::srgba(0.75, 0.9, 0.95, 0.7),
+            SingleBlockType::Tnt => Color::srgb(0.9, 0.5, 0.1),
+        }
+    }
+
+    /// whether the block drops when there's nothing under it
+    fn falls(&self) -> bool {
+        matches!(self, SingleBlockType::Sand)
+    }
+
+    fn flammable(&self) -> bool {
+        matches!(self, SingleBlockType::Wood | SingleBlockType::Grass)
+    }
+
+    fn explodes(&self) -> bool {
+        matches!(self, SingleBlockType::Tnt)
+    }
+
+    /// price for a player to build one, `None` if it can only come with the map
+    fn cost(&self) -> Option<u32> {
+        match self {
+            SingleBlockType::Dirt => Some(DIRT_COST),
+            SingleBlockType::Grass => Some(GRASS_COST),
+            SingleBlockType::Stone => Some(STONE_COST),
+            SingleBlockType::Metal => Some(METAL_COST),
+            SingleBlockType::Wood => Some(WOOD_COST),
+            SingleBlockType::Sand => Some(SAND_COST),
+            SingleBlockType::Bedrock => None,
+            SingleBlockType::Glass => Some(GLASS_COST),
+            SingleBlockType::Tnt => Some(TNT_COST),
+        }
+    }
+
+    /// one line summary of the material's properties
+    fn describe(&self) -> String {
+        let mut traits = Vec::new();
+        if self.health() == BEDROCK_HEALTH {
+            traits.push("indestructible".to_string());
+        } else {
+            traits.push(format!("{} hp", self.health()));
+        }
+        if self.falls() {
+            traits.push("falls".to_string());
+        }
+        if self.flammable() {
+            traits.push("burns".to_string());
+        }
+        if self.explodes() {
+            traits.push("explodes".to_string());
+        }
+        if let Some(cost) = self.cost() {
+            traits.push(format!("${cost}"));
+        }
+        format!("{}: {}", self.name(), traits.join(", "))
+    }
+
+    /// character used for this block in map files
+    fn symbol(&self) -> char {
+        match self {
+            SingleBlockType::Dirt => 'd',
+            SingleBlockType::Grass => 'g',
+            SingleBlockType::Stone => 's',
+            SingleBlockType::Metal => 'm',
+            SingleBlockType::Wood => 'w',
+            SingleBlockType::Sand => 'a',
+            SingleBlockType::Bedrock => 'b',
+            SingleBlockType::Glass => 'i',
+            SingleBlockType::Tnt => 't',
+        }
+    }
+
+    fn from_symbol(symbol: char) -> Option<SingleBlockType> {
+        SingleBlockType::iter().find(|block_type| block_type.symbol() == symbol)
+    }
+
+    fn iter() -> impl Iterator<Item = SingleBlockType> {
+        [
+            SingleBlockType::Dirt,
+            SingleBlockType::Grass,
+            SingleBlockType::Stone,
+            SingleBlockType::Metal,
+            SingleBlockType::Wood,
+            SingleBlockType::Sand,
+            SingleBlockType::Bedrock,
+            SingleBlockType::Glass,
+            SingleBlockType::Tnt,
+        ]
+        .iter()
+        .copied()
+    }
+}
+
+/// Size of the play area in grid cells and where the two sides meet.
+/// Comes from the selected map file.
+#[derive(Resource, Debug, Clone, Copy)]
+struct Battlefield {
+    width: i32,
+    height: i32,
+    /// rows below this belong to the bottom player, the rest to the top player
+    divider: i32,
+}
+
+impl Battlefield {
+    fn size(&self) -> Vec2 {
+        Vec2::new(
+            self.width as f32 * GRID_SIZE.x,
+            self.height as f32 * GRID_SIZE.y,
+        )
+    }
+
+    fn contains(&self, position: I64Vec2) -> bool {
+        position.x >= 0
+            && position.y >= 0
+            && position.x < self.width as i64
+            && position.y < self.height as i64
+    }
+
+    fn side_of(&self, position: I64Vec2) -> PlayerSide {
+        if position.y < self.divider as i64 {
+            PlayerSide::Bottom
+        } else {
+            PlayerSide::Top
+        }
+    }
+}
+
+fn spawn_block(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    battlefield: &Battlefield,
+    block_type: SingleBlockType,
+    grid_position: I64Vec2,
+    player_side: PlayerSide,
+) -> Entity {
+    let block_texture = block_type.image(asset_server);
+    let footprint = Purchasable::Block(block_type)
+        .footprint()
+        .for_side(player_side);
+    commands
+        .spawn((
+            Sprite {
+                image: block_texture,
+                flip_y: player_side.flip_y(),
+                custom_size: Some(footprint.world_size()),
+                ..default()
+            },
+            Transform::from_translation(footprint.center(battlefield, grid_position).extend(0.0)),
+            GlobalTransform::default(),
+            Visibility::default(),
+            Board { player_side },
+            Block { block_type },
+            Breakable::new(block_type.health()),
+            footprint.grid(grid_position),
+        ))
+        .id()
+}
+
+fn spawn_purchasable(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    battlefield: &Battlefield,
+    item: Purchasable,
+    player_side: PlayerSide,
+    grid_position: I64Vec2,
+) -> Entity {
+    let entity = match item {
+        Purchasable::Launcher(kind) => launcher::spawn_launcher(
+            commands,
+            asset_server,
+            battlefield,
+            kind,
+            player_side,
+            grid_position,
+        ),
+        Purchasable::Defense(kind) => defense::spawn_defense(
+            commands,
+            asset_server,
+            battlefield,
+            kind,
+            player_side,
+            grid_position,
+        ),
+        Purchasable::Board => spawn_board(
+            commands,
+            asset_server,
+            battlefield,
+            player_side,
+            grid_position,
+        ),
+        Purchasable::Block(block_type) => spawn_block(
+            commands,
+            asset_server,
+            battlefield,
+            block_type,
+            grid_position,
+            player_side,
+        ),
+    };
+    commands.entity(entity).insert(Structure { item });
+    entity
+}
+
+/// The map picked on the map select screen.
+#[derive(Resource)]
+struct SelectedMap(MapDefinition);
+
+fn spawn_board(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    battlefield: &Battlefield,
+    player_side: PlayerSide,
+    grid_position: I64Vec2,
+) -> Entity {
+    let footprint = Purchasable::Board.footprint().for_side(player_side);
+    commands
+        .spawn((
+            Sprite {
+                image: asset_server.load("board.png"),
+                flip_y: player_side.flip_y(),
+                custom_size: Some(footprint.world_size()),
+                ..default()
+            },
+            Transform::from_translation(footprint.center(battlefield, grid_position).extend(0.0)),
+            GlobalTransform::default(),
+            Visibility::default(),
+            Board { player_side },
+            Breakable::new(BOARD_HEALTH),
+            footprint.grid(grid_position),
+        ))
+        .id()
+}
+
+fn from_grid_coords(battlefield: &Battlefield, position: I64Vec2) -> Vec2 {
+    let size = battlefield.size();
+    Vec2::new(
+        position.x as f32 * GRID_SIZE.x - size.x / 2.0 + GRID_SIZE.x / 2.0,
+        position.y as f32 * GRID_SIZE.y - size.y / 2.0 + GRID_SIZE.y / 2.0,
+    )
+}
+
+fn to_grid_coords(battlefield: &Battlefield, position: Vec2) -> I64Vec2 {
+    let size = battlefield.size();
+    I64Vec2::new(
+        ((position.x + size.x / 2.0) / GRID_SIZE.x).floor() as i64,
+        ((position.y + size.y / 2.0) / GRID_SIZE.y).floor() as i64,
+    )
+}
+
+#[derive(Component)]
+struct PurchaseMenuButton;
+#[derive(Component)]
+struct PurchaseButton {
+    item: Purchasable,
+}
+
+#[derive(Component)]
+struct Player {
+    side: PlayerSide,
+    money: u32,
+    /// actions left this turn
+    actions: u32,
+    /// health lost by everything on their side so far
+    damage_taken: u32,
+    /// earned for knocking down enemy structures, paid with their next income
+    bounty: u32,
+    last_income: IncomeBreakdown,
+    state: PlayerState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlayerState {
+    WaitingForAction,
+    WaitingForTurn,
+    PurchaseMenu,
+    Placing {
+        item: Purchasable,
+    },
+    /// picking a new spot for one of their structures
+    Moving {
+        entity: Entity,
+        item: Purchasable,
+    },
+}
+
+/// The player whose turn it is.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Deref)]
+struct ActivePlayer(Entity);
+
+#[derive(Component)]
+struct Turn {
+    player_side: PlayerSide,
+    /// counts up from 1, both players' turns included
+    number: u32,
+}
+
+#[derive(Event)]
+struct EndTurn;
+
+/// `change_turn` just handed the turn over to `side`, for anything that happens once a turn.
+#[derive(Event)]
+struct TurnStarted {
+    side: PlayerSide,
+}
+
+/// Something blew up at this cell, a TNT block or a mortar shell.
+#[derive(Event)]
+struct Explosion {
+    position: I64Vec2,
+    /// whose shot set it off, `None` when nobody's did
+    attacker: Option<PlayerSide>,
+    /// where that shot was fired from, in sub-cells
+    origin: I64Vec2,
+}
+
+#[derive(Component)]
+struct Board {
+    player_side: PlayerSide,
+}
+
+#[derive(Component)]
+struct MainCamera;
+
+#[derive(Component)]
+struct Background;
+
+/// How far a shot moves each tick, in sub-cells.
+#[derive(Component, Deref, DerefMut)]
+struct Velocity(I64Vec2);
+
+#[derive(Component)]
+struct Block {
+    block_type: SingleBlockType,
+}
+
+#[derive(Component)]
+struct Breakable {
+    health: u8,
+    max_health: u8,
+}
+
+impl Breakable {
+    fn new(health: u8) -> Breakable {
+        Breakable {
+            health,
+            max_health: health,
+        }
+    }
+
+    /// 0 when intact, 1 when broken
+    fn damage_fraction(&self) -> f32 {
+        1.0 - self.health as f32 / self.max_health as f32
+    }
+}
+
+/// Shots that hit whatever they touch on the way.
+#[derive(Component)]
+struct Collider;
+
+#[derive(Component)]
+struct Menu;
+
+#[derive(Component)]
+struct CannonBall {
+    player_side: PlayerSide,
+    ammo: AmmoType,
+    /// where it was fired from, in sub-cells
+    origin: I64Vec2,
+    /// what it bounced off or went through last, so it isn't hit again on the way out
+    last_hit: Option<Entity>,
+}
+
+#[derive(Component)]
+struct Grid {
+    positions: Vec<I64Vec2>,
+    /// covered cells that take extra damage
+    weak_points: Vec<I64Vec2>,
+}
+
+/// The grid cells something covers, relative to the cell it's placed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Footprint {
+    width: i64,
+    height: i64,
+    /// bit `y * width + x` is set for every covered cell of the bounding box, counted
+    /// from its lower left
+    mask: u64,
+    /// same layout as `mask`, set for the cells hits there do extra damage
+    weak: u64,
+    /// cell of the bounding box that goes on the grid position it's placed at, the same
+    /// on both sides so map files don't change meaning
+    anchor: I64Vec2,
+}
+
+impl Footprint {
+    const fn new(width: i64, height: i64, mask: u64, anchor: I64Vec2) -> Footprint {
+        Footprint {
+            width,
+            height,
+            mask,
+            weak: 0,
+            anchor,
+        }
+    }
+
+    const fn with_weak_points(self, weak: u64) -> Footprint {
+        Footprint { weak, ..self }
+    }
+
+    /// every cell of a `width` by `height` box, placed by its lower left
+    const fn rect(width: i64, height: i64) -> Footprint {
+        Footprint::new(
+            width,
+            height,
+            u64::MAX >> (64 - width * height),
+            I64Vec2::ZERO,
+        )
+    }
+
+    /// the top player's things are drawn upside down, so their shapes are too
+    fn for_side(self, side: PlayerSide) -> Footprint {
+        if !side.flip_y() {
+            return self;
+        }
+        Footprint {
+            mask: self.flip_rows(self.mask),
+            weak: self.flip_rows(self.weak),
+            ..self
+        }
+    }
+
+    fn flip_rows(&self, bits: u64) -> u64 {
+        let row = u64::MAX >> (64 - self.width);
+        (0..self.height).fold(0, |flipped, y| {
+            let bits = (bits >> (y * self.width)) & row;
+            flipped | bits << ((self.height - 1 - y) * self.width)
+        })
+    }
+
+    fn size(&self) -> I64Vec2 {
+        I64Vec2::new(self.width, self.height)
+    }
+
+    fn world_size(&self) -> Vec2 {
+        self.size().as_vec2() * GRID_SIZE
+    }
+
+    /// covered cells when placed at `grid_position`, bottom row first
+    fn cells(self, grid_position: I64Vec2) -> impl Iterator<Item = I64Vec2> {
+        self.cells_in(self.mask, grid_position)
+    }
+
+    fn weak_points(self, grid_position: I64Vec2) -> impl Iterator<Item = I64Vec2> {
+        self.cells_in(self.mask & self.weak, grid_position)
+    }
+
+    fn cells_in(self, bits: u64, grid_position: I64Vec2) -> impl Iterator<Item = I64Vec2> {
+        let lower_left = grid_position - self.anchor;
+        (0..self.height)
+            .flat_map(move |y| (0..self.width).map(move |x| I64Vec2::new(x, y)))
+            .filter(move |cell| bits & (1 << (cell.y * self.width + cell.x)) != 0)
+            .map(move |cell| lower_left + cell)
+    }
+
+    fn grid(&self, grid_position: I64Vec2) -> Grid {
+        Grid {
+            positions: self.cells(grid_position).collect(),
+            weak_points: self.weak_points(grid_position).collect(),
+        }
+    }
+
+    /// world position of the middle of the bounding box when placed at `grid_position`
+    fn center(&self, battlefield: &Battlefield, grid_position: I64Vec2) -> Vec2 {
+        let lower_left = grid_position - self.anchor;
+        from_grid_coords(battlefield, lower_left)
+            + (self.size() - I64Vec2::ONE).as_vec2() * GRID_SIZE / 2.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+enum Purchasable {
+    Launcher(LauncherKind),
+    Defense(DefenseKind),
+    Board,
+    Block(SingleBlockType),
+}
+
+impl Purchasable {
+    /// what it's sold for in the shop, `None` for blocks that only come with the map
+    fn price(&self) -> Option<u32> {
+        match self {
+            Purchasable::Launcher(kind) => Some(kind.cost()),
+            Purchasable::Defense(kind) => Some(kind.cost()),
+            Purchasable::Board => Some(BOARD_COST),
+            Purchasable::Block(block_type) => block_type.cost(),
+        }
+    }
+
+    fn cost(&self) -> u32 {
+        // blocks that aren't for sale are never affordable
+        self.price().unwrap_or(u32::MAX)
+    }
+
+    /// grid cells taken up, as seen by the bottom player
+    fn footprint(&self) -> Footprint {
+        match self {
+            Purchasable::Launcher(kind) => kind.footprint(),
+            Purchasable::Defense(kind) => kind.footprint(),
+            Purchasable::Board | Purchasable::Block(_) => Footprint::rect(1, 1),
+        }
+    }
+
+    /// one line of stats for the shop tooltip
+    fn describe(&self) -> String {
+        match self {
+            Purchasable::Launcher(kind) => kind.describe(),
+            Purchasable::Defense(kind) => kind.describe(),
+            Purchasable::Board => format!("Board: {BOARD_HEALTH} hp, ${BOARD_COST}"),
+            Purchasable::Block(block_type) => block_type.describe(),
+        }
+    }
+
+    fn image(&self, asset_server: &AssetServer) -> Handle<Image> {
+        match self {
+            Purchasable::Launcher(kind) => kind.image(asset_server),
+            Purchasable::Defense(kind) => kind.image(asset_server),
+            Purchasable::Board => asset_server.load("board.png"),
+            Purchasable::Block(block_type) => block_type.image(asset_server),
+        }
+    }
+
+    fn iter() -> impl Iterator<Item = Purchasable> {
+        LauncherKind::iter()
+            .map(Purchasable::Launcher)
+            .chain(DefenseKind::iter().map(Purchasable::Defense))
+            .chain([Purchasable::Board])
+            .chain(
+                SingleBlockType::iter()
+                    .filter(|block_type| block_type.cost().is_some())
+                    .map(Purchasable::Block),
+            )
+    }
+}
+
+impl ToString for Purchasable {
+    fn to_string(&self) -> String {
+        match self {
+            Purchasable::Launcher(kind) => kind.name().to_string(),
+            Purchasable::Defense(kind) => kind.name().to_string(),
+            Purchasable::Board => "Board".to_string(),
+            Purchasable::Block(block_type) => block_type.name().to_string(),
+        }
+    }
+}
@@ -0,0 +1,667 @@
+//! Hand-authored battlefields.
+//!
+//! Maps live in `assets/maps/*.map.ron`. The grid itself is written as ASCII rows (top row
+//! first) so a map can be drawn in a text editor, everything else is plain RON:
+//!
+//! ```ron
+//! (
+//!     name: "Classic",
+//!     width: 20,
+//!     height: 40,
+//!     divider: 20,
+//!     rows: [
+//!         "dddddddddddddddddddd",
+//!         // ...
+//!     ],
+//!     structures: [
+//!         (kind: Launcher(Cannon), side: Bottom, position: (3, 5)),
+//!     ],
+//! )
+//! ```
+//!
+//! `.` is an empty cell, blocks are `d` dirt, `g` grass, `s` stone, `m` metal, `w` wood,
+//! `a` sand, `b` bedrock, `i` glass and `t` TNT.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::io::file::FileAssetReader;
+use bevy::{math::I64Vec2, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::editor::EditorMap;
+use crate::{
+    AppState, Battlefield, NORMAL_BUTTON, PlayerSide, Purchasable, SelectedMap, SingleBlockType,
+};
+
+const MAPS_DIR: &str = "maps";
+pub const MAP_EXTENSION: &str = ".map.ron";
+pub const EMPTY_SYMBOL: char = '.';
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MapDefinition {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    /// rows below this belong to the bottom player
+    pub divider: i32,
+    /// one string per grid row, top row first
+    pub rows: Vec<String>,
+    #[serde(default)]
+    pub structures: Vec<MapStructure>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct MapStructure {
+    pub kind: Purchasable,
+    pub side: PlayerSide,
+    /// lower left grid cell
+    pub position: (i64, i64),
+}
+
+#[derive(Debug)]
+pub enum MapError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Serialize(ron::Error),
+    BadSize {
+        width: i32,
+        height: i32,
+    },
+    BadDivider(i32),
+    RowCount {
+        expected: i32,
+        found: usize,
+    },
+    RowWidth {
+        row: usize,
+        expected: i32,
+        found: usize,
+    },
+    UnknownSymbol {
+        row: usize,
+        column: usize,
+        symbol: char,
+    },
+    StructureOutOfBounds {
+        position: (i64, i64),
+    },
+    /// some of it is on the other player's half
+    StructureWrongSide {
+        position: (i64, i64),
+    },
+    /// it covers a cell an earlier structure already does
+    StructuresOverlap {
+        position: (i64, i64),
+    },
+    /// it covers a cell that has a block in `rows`
+    StructureOverBlock {
+        position: (i64, i64),
+    },
+    /// it's something players can't buy, like bedrock
+    StructureNotForSale {
+        position: (i64, i64),
+    },
+}
+
+impl fmt::Display for MapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MapError::Io(err) => write!(f, "could not read map: {err}"),
+            MapError::Parse(err) => write!(f, "could not parse map: {err}"),
+            MapError::Serialize(err) => write!(f, "could not write map: {err}"),
+            MapError::BadSize { width, height } => {
+                write!(f, "map size {width}x{height} must be positive")
+            }
+            MapError::BadDivider(divider) => {
+                write!(f, "divider {divider} must be inside the map")
+            }
+            MapError::RowCount { expected, found } => {
+                write!(f, "expected {expected} rows, found {found}")
+            }
+            MapError::RowWidth {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {row} should be {expected} cells wide, found {found}"
+            ),
+            MapError::UnknownSymbol {
+                row,
+                column,
+                symbol,
+            } => write!(f, "unknown symbol {symbol:?} at row {row}, column {column}"),
+            MapError::StructureOutOfBounds { position } => {
+                write!(f, "structure at {position:?} is outside the map")
+            }
+            MapError::StructureWrongSide { position } => {
+                write!(f, "structure at {position:?} crosses onto the other side")
+            }
+            MapError::StructuresOverlap { position } => {
+                write!(f, "structure at {position:?} overlaps another structure")
+            }
+            MapError::StructureOverBlock { position } => {
+                write!(f, "structure at {position:?} overlaps a block")
+            }
+            MapError::StructureNotForSale { position } => {
+                write!(
+                    f,
+                    "structure at {position:?} isn't something players can buy"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for MapError {}
+
+impl MapDefinition {
+    pub fn parse(source: &str) -> Result<MapDefinition, MapError> {
+        let map: MapDefinition = ron::from_str(source).map_err(MapError::Parse)?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    pub fn load(path: &Path) -> Result<MapDefinition, MapError> {
+        let source = fs::read_to_string(path).map_err(MapError::Io)?;
+        MapDefinition::parse(&source)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), MapError> {
+        self.validate()?;
+        let source = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(MapError::Serialize)?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(MapError::Io)?;
+        }
+        fs::write(path, source).map_err(MapError::Io)
+    }
+
+    fn validate(&self) -> Result<(), MapError> {
+        if self.width <= 0 || self.height <= 0 {
+            return Err(MapError::BadSize {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        if self.divider <= 0 || self.divider >= self.height {
+            return Err(MapError::BadDivider(self.divider));
+        }
+        if self.rows.len() != self.height as usize {
+            return Err(MapError::RowCount {
+                expected: self.height,
+                found: self.rows.len(),
+            });
+        }
+        for (row, cells) in self.rows.iter().enumerate() {
+            let found = cells.chars().count();
+            if found != self.width as usize {
+                return Err(MapError::RowWidth {
+                    row,
+                    expected: self.width,
+                    found,
+                });
+            }
+            if let Some((column, symbol)) = cells.chars().enumerate().find(|(_, symbol)| {
+                *symbol != EMPTY_SYMBOL && SingleBlockType::from_symbol(*symbol).is_none()
+            }) {
+                return Err(MapError::UnknownSymbol {
+                    row,
+                    column,
+                    symbol,
+                });
+            }
+        }
+        let battlefield = self.battlefield();
+        let blocks: HashSet<I64Vec2> = self.blocks().map(|(position, _)| position).collect();
+        let mut covered = HashSet::new();
+        for structure in &self.structures {
+            let (x, y) = structure.position;
+            let cells: Vec<I64Vec2> = structure
+                .kind
+                .footprint()
+                .for_side(structure.side)
+                .cells(I64Vec2::new(x, y))
+                .collect();
+            let position = structure.position;
+            if structure.kind.price().is_none() {
+                return Err(MapError::StructureNotForSale { position });
+            }
+            if !cells.iter().all(|cell| battlefield.contains(*cell)) {
+                return Err(MapError::StructureOutOfBounds { position });
+            }
+            if cells
+                .iter()
+                .any(|cell| battlefield.side_of(*cell) != structure.side)
+            {
+                return Err(MapError::StructureWrongSide { position });
+            }
+            if cells.iter().any(|cell| blocks.contains(cell)) {
+                return Err(MapError::StructureOverBlock { position });
+            }
+            if !cells.into_iter().all(|cell| covered.insert(cell)) {
+                return Err(MapError::StructuresOverlap { position });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn battlefield(&self) -> Battlefield {
+        Battlefield {
+            width: self.width,
+            height: self.height,
+            divider: self.divider,
+        }
+    }
+
+    /// every block in the map with its grid position
+    pub fn blocks(&self) -> impl Iterator<Item = (I64Vec2, SingleBlockType)> + '_ {
+        let height = self.rows.len() as i64;
+        self.rows.iter().enumerate().flat_map(move |(row, cells)| {
+            let y = height - row as i64 - 1;
+            cells.chars().enumerate().filter_map(move |(x, symbol)| {
+                SingleBlockType::from_symbol(symbol)
+                    .map(|block_type| (I64Vec2::new(x as i64, y), block_type))
+            })
+        })
+    }
+}
+
+/// A map file found on disk, which may have failed to load.
+pub struct MapEntry {
+    pub path: PathBuf,
+    pub map: Result<MapDefinition, MapError>,
+}
+
+#[derive(Resource, Default)]
+pub struct AvailableMaps(pub Vec<MapEntry>);
+
+pub fn maps_dir() -> PathBuf {
+    FileAssetReader::get_base_path()
+        .join("assets")
+        .join(MAPS_DIR)
+}
+
+fn is_map_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(MAP_EXTENSION))
+}
+
+pub fn discover_maps(mut commands: Commands) {
+    let mut entries: Vec<MapEntry> = match fs::read_dir(maps_dir()) {
+        Ok(dir) => dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| is_map_file(path))
+            .map(|path| MapEntry {
+                map: MapDefinition::load(&path),
+                path,
+            })
+            .collect(),
+        Err(err) => {
+            warn!("could not read maps directory {:?}: {err}", maps_dir());
+            Vec::new()
+        }
+    };
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    commands.insert_resource(AvailableMaps(entries));
+}
+
+#[derive(Component)]
+pub struct MapSelectMenu;
+
+#[derive(Component)]
+pub struct MapButton {
+    index: usize,
+}
+
+/// Opens the editor on an existing map, or on a blank one when `index` is `None`.
+#[derive(Component)]
+pub struct EditMapButton {
+    index: Option<usize>,
+}
+
+pub fn spawn_menu_button(
+    parent: &mut ChildSpawnerCommands,
+    label: String,
+    width: f32,
+    marker: impl Bundle,
+) {
+    parent
+        .spawn((
+            Button,
+            Node {
+                width: Val::Px(width),
+                height: Val::Px(40.0),
+                border: UiRect::all(Val::Px(5.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderColor(Color::BLACK),
+            BackgroundColor(NORMAL_BUTTON),
+            BorderRadius::MAX,
+            marker,
+        ))
+        .with_children(|btn| {
+            btn.spawn((
+                Text::new(label),
+                TextFont {
+                    font_size: 20.0,
+                    ..default()
+                },
+                TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                TextShadow::default(),
+            ));
+        });
+}
+
+pub fn spawn_map_select(mut commands: Commands, maps: Res<AvailableMaps>) {
+    commands
+        .spawn((
+            Node {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            MapSelectMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new("Choose a map"),
+                TextFont {
+                    font_size: 30.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+
+            if maps.0.is_empty() {
+                parent.spawn((
+                    Text::new(format!("No maps found in {}", maps_dir().display())),
+                    TextFont {
+                        font_size: 16.0,
+                        ..default()
+                    },
+                    TextColor(Color::srgb(0.9, 0.4, 0.4)),
+                ));
+            }
+
+            for (index, entry) in maps.0.iter().enumerate() {
+                match &entry.map {
+                    Ok(map) => {
+                        parent
+                            .spawn(Node {
+                                column_gap: Val::Px(8.0),
+                                ..default()
+                            })
+                            .with_children(|row| {
+                                spawn_menu_button(
+                                    row,
+                                    format!("{} ({}x{})", map.name, map.width, map.height),
+                                    300.0,
+                                    MapButton { index },
+                                );
+                                spawn_menu_button(
+                                    row,
+                                    "Edit".to_string(),
+                                    80.0,
+                                    EditMapButton { index: Some(index) },
+                                );
+                            });
+                    }
+                    Err(err) => {
+                        let file = entry
+                            .path
+                            .file_name()
+                            .map(|name| name.to_string_lossy())
+                            .unwrap_or_default();
+                        parent.spawn((
+                            Text::new(format!("{file}: {err}")),
+                            TextFont {
+                                font_size: 16.0,
+                                ..default()
+                            },
+                            TextColor(Color::srgb(0.9, 0.4, 0.4)),
+                        ));
+                    }
+                }
+            }
+
+            spawn_menu_button(
+                parent,
+                "New map".to_string(),
+                388.0,
+                EditMapButton { index: None },
+            );
+        });
+}
+
+pub fn despawn_map_select(mut commands: Commands, menus: Query<Entity, With<MapSelectMenu>>) {
+    for e in &menus {
+        commands.entity(e).despawn();
+    }
+}
+
+pub fn choose_map(
+    mut commands: Commands,
+    interactions: Query<(&Interaction, &MapButton), Changed<Interaction>>,
+    maps: Res<AvailableMaps>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some(map) = interactions
+        .iter()
+        .find(|(i, _)| **i == Interaction::Pressed)
+        .and_then(|(_, button)| maps.0[button.index].map.as_ref().ok())
+    else {
+        return;
+    };
+
+    commands.insert_resource(map.battlefield());
+    commands.insert_resource(SelectedMap(map.clone()));
+    next_state.set(AppState::InGame);
+}
+
+pub fn open_editor(
+    mut commands: Commands,
+    interactions: Query<(&Interaction, &EditMapButton), Changed<Interaction>>,
+    maps: Res<AvailableMaps>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let Some((_, button)) = interactions
+        .iter()
+        .find(|(i, _)| **i == Interaction::Pressed)
+    else {
+        return;
+    };
+
+    let editor_map = match button.index {
+        Some(index) => {
+            let entry = &maps.0[index];
+            let Ok(map) = &entry.map else {
+                return;
+            };
+            EditorMap::from_definition(map, entry.path.clone())
+        }
+        None => {
+            let taken: Vec<&str> = maps
+                .0
+                .iter()
+                .filter_map(|entry| entry.map.as_ref().ok())
+                .map(|map| map.name.as_str())
+                .collect();
+            // unreadable maps have no name but still have a file that mustn't be overwritten
+            let name = (1..)
+                .map(|n| format!("Untitled {n}"))
+                .find(|name| {
+                    let path = EditorMap::path_for(name);
+                    !taken.contains(&name.as_str())
+                        && !maps.0.iter().any(|entry| entry.path == path)
+                        && !path.exists()
+                })
+                .unwrap();
+            EditorMap::blank(name)
+        }
+    };
+
+    commands.insert_resource(editor_map.battlefield());
+    commands.insert_resource(editor_map);
+    next_state.set(AppState::Editor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// map file text for a map of `size` with `structures` on it
+    fn map(size: (i32, i32), divider: i32, rows: &[&str], structures: &str) -> String {
+        let rows: Vec<String> = rows.iter().map(|row| format!("{row:?}")).collect();
+        format!(
+            "(name: \"Test\", width: {}, height: {}, divider: {divider}, rows: [{}], \
+             structures: [{structures}])",
+            size.0,
+            size.1,
+            rows.join(", ")
+        )
+    }
+
+    /// 4x4, with ground at the top and bottom
+    const ROWS: [&str; 4] = ["dddd", "....", "....", "dddd"];
+
+    fn parse(structures: &str) -> Result<MapDefinition, MapError> {
+        MapDefinition::parse(&map((4, 4), 2, &ROWS, structures))
+    }
+
+    #[test]
+    fn a_good_map_parses() {
+        let map = parse("(kind: Block(Stone), side: Bottom, position: (1, 1))").unwrap();
+        assert_eq!(map.structures.len(), 1);
+        assert_eq!(map.battlefield().divider, 2);
+    }
+
+    #[test]
+    fn shipped_maps_are_valid() {
+        for name in ["classic", "fortress", "quarry"] {
+            let path = format!("assets/maps/{name}{MAP_EXTENSION}");
+            if let Err(err) = MapDefinition::load(Path::new(&path)) {
+                panic!("{path}: {err}");
+            }
+        }
+    }
+
+    #[test]
+    fn broken_ron_is_a_parse_error() {
+        assert!(matches!(
+            MapDefinition::parse("(name: \"Test\""),
+            Err(MapError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn size_must_be_positive() {
+        assert!(matches!(
+            MapDefinition::parse(&map((0, 4), 2, &ROWS, "")),
+            Err(MapError::BadSize {
+                width: 0,
+                height: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn divider_must_be_inside_the_map() {
+        for divider in [0, 4] {
+            assert!(matches!(
+                MapDefinition::parse(&map((4, 4), divider, &ROWS, "")),
+                Err(MapError::BadDivider(d)) if d == divider
+            ));
+        }
+    }
+
+    #[test]
+    fn every_row_must_be_there() {
+        assert!(matches!(
+            MapDefinition::parse(&map((4, 4), 2, &ROWS[..3], "")),
+            Err(MapError::RowCount {
+                expected: 4,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn rows_must_be_the_map_width() {
+        let rows = ["dddd", "...", "....", "dddd"];
+        assert!(matches!(
+            MapDefinition::parse(&map((4, 4), 2, &rows, "")),
+            Err(MapError::RowWidth {
+                row: 1,
+                expected: 4,
+                found: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn unknown_symbols_are_rejected() {
+        let rows = ["dddd", "..x.", "....", "dddd"];
+        assert!(matches!(
+            MapDefinition::parse(&map((4, 4), 2, &rows, "")),
+            Err(MapError::UnknownSymbol {
+                row: 1,
+                column: 2,
+                symbol: 'x'
+            })
+        ));
+    }
+
+    #[test]
+    fn structures_must_be_on_the_map() {
+        assert!(matches!(
+            parse("(kind: Block(Stone), side: Bottom, position: (4, 1))"),
+            Err(MapError::StructureOutOfBounds { position: (4, 1) })
+        ));
+    }
+
+    #[test]
+    fn structures_must_be_on_their_own_side() {
+        assert!(matches!(
+            parse("(kind: Block(Stone), side: Top, position: (1, 1))"),
+            Err(MapError::StructureWrongSide { position: (1, 1) })
+        ));
+    }
+
+    #[test]
+    fn structures_must_not_overlap() {
+        assert!(matches!(
+            parse(
+                "(kind: Block(Stone), side: Bottom, position: (1, 1)), \
+                 (kind: Block(Metal), side: Bottom, position: (1, 1))"
+            ),
+            Err(MapError::StructuresOverlap { position: (1, 1) })
+        ));
+    }
+
+    #[test]
+    fn structures_must_not_cover_blocks() {
+        assert!(matches!(
+            parse("(kind: Block(Stone), side: Bottom, position: (1, 0))"),
+            Err(MapError::StructureOverBlock { position: (1, 0) })
+        ));
+    }
+
+    #[test]
+    fn structures_must_be_for_sale() {
+        assert!(matches!(
+            parse("(kind: Block(Bedrock), side: Bottom, position: (1, 1))"),
+            Err(MapError::StructureNotForSale { position: (1, 1) })
+        ));
+    }
+}
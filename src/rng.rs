@@ -0,0 +1,207 @@
+//! All of the game's randomness, from one seed.
+//!
+//! Every subsystem rolls from its own stream forked off the seed, so rolling more in
+//! one place doesn't change what happens in another, and a match started with the same
+//! seed and played the same way ends the same. `--seed <n>` picks the seed, otherwise a
+//! random one is used and logged so the match can be played again.
+
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// spreads the stream numbers out before they're mixed into the seed
+const STREAM_MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// The parts of the game that roll dice, each gets its own stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    Weather,
+    Fire,
+    Defense,
+}
+
+/// The match's seed and the streams forked from it. ChaCha8 rolls the same on every
+/// platform and `rand` version, `StdRng` doesn't promise to.
+#[derive(Resource, Debug)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+/// A random seed, for when the binary didn't pick one.
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(rand::random())
+    }
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> GameRng {
+        GameRng {
+            seed,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// seeded from `--seed <n>` in `args`, or randomly without it, an error if `n` isn't a
+    /// number so a typo doesn't quietly play a different match
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<GameRng, String> {
+        let mut args = args.into_iter().skip_while(|arg| arg != "--seed");
+        if args.next().is_none() {
+            return Ok(GameRng::default());
+        }
+        match args.next() {
+            Some(seed) => seed
+                .parse()
+                .map(GameRng::new)
+                .map_err(|_| format!("--seed needs a number, not `{seed}`")),
+            None => Err("--seed needs a number".to_string()),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// the rolls for `stream`, carrying on from where it last left off
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let seed = self.seed ^ (stream as u64 + 1).wrapping_mul(STREAM_MIX);
+        self.streams
+            .entry(stream)
+            .or_insert_with(|| ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// start every stream over from the seed
+    pub fn restart(&mut self) {
+        self.streams.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::I64Vec2;
+    use rand::Rng;
+
+    use super::*;
+    use crate::PlayerSide;
+    use crate::harness::{Harness, Snapshot};
+
+    fn args(args: &[&str]) -> Result<GameRng, String> {
+        GameRng::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn seed_comes_from_the_command_line() {
+        assert_eq!(args(&["launch-game", "--seed", "42"]).unwrap().seed(), 42);
+        assert_eq!(
+            args(&["launch-game", "--hardcore", "--seed", "7", "--no-weather"])
+                .unwrap()
+                .seed(),
+            7
+        );
+        assert!(args(&["launch-game", "--hardcore"]).is_ok());
+    }
+
+    #[test]
+    fn bad_seed_is_rejected() {
+        assert!(args(&["launch-game", "--seed", "seven"]).is_err());
+        assert!(args(&["launch-game", "--seed", "-1"]).is_err());
+        assert!(args(&["launch-game", "--seed"]).is_err());
+    }
+
+    #[test]
+    fn same_seed_rolls_the_same() {
+        let mut a = GameRng::new(3);
+        let mut b = GameRng::new(3);
+        let rolls = |rng: &mut GameRng| -> Vec<u32> {
+            (0..10)
+                .map(|_| rng.stream(RngStream::Fire).r#gen())
+                .collect()
+        };
+        assert_eq!(rolls(&mut a), rolls(&mut b));
+    }
+
+    #[test]
+    fn streams_dont_affect_each_other() {
+        let mut a = GameRng::new(3);
+        let mut b = GameRng::new(3);
+        for _ in 0..10 {
+            a.stream(RngStream::Weather).r#gen::<u32>();
+        }
+        assert_eq!(
+            a.stream(RngStream::Fire).r#gen::<u32>(),
+            b.stream(RngStream::Fire).r#gen::<u32>()
+        );
+    }
+
+    #[test]
+    fn restarting_rolls_the_same_again() {
+        let mut rng = GameRng::new(9);
+        let first: u32 = rng.stream(RngStream::Defense).r#gen();
+        rng.restart();
+        assert_eq!(rng.stream(RngStream::Defense).r#gen::<u32>(), first);
+    }
+
+    /// a cannon on each side shooting at the other's stones, the same shots every time
+    const MAP: &str = r#"(
+        name: "Duel",
+        width: 8,
+        height: 16,
+        divider: 8,
+        rows: [
+            "dddddddd",
+            "dddddddd",
+            "........",
+            "........",
+            "..ss.ss.",
+            "........",
+            "........",
+            "........",
+            "........",
+            "........",
+            "........",
+            ".ss.ss..",
+            "........",
+            "........",
+            "dddddddd",
+            "dddddddd",
+        ],
+        structures: [
+            (kind: Launcher(Cannon), side: Bottom, position: (3, 2)),
+            (kind: Launcher(Cannon), side: Top, position: (4, 12)),
+        ],
+    )"#;
+    const TURNS: usize = 12;
+    const FLIGHT_TICKS: usize = 60;
+
+    /// play the same turns with the weather rolled from `seed`, and how the match ended
+    fn play(seed: u64) -> Snapshot {
+        let mut game = Harness::with_weather(MAP, seed);
+        for turn in 0..TURNS {
+            let cannon = match game.turn().1 {
+                PlayerSide::Bottom => I64Vec2::new(3, 2),
+                PlayerSide::Top => I64Vec2::new(4, 12),
+            };
+            // until the weather knocks it down
+            if game.health_at(cannon).is_some() {
+                game.select(cannon);
+                game.fire((turn as i64 - 6) * 50, 100);
+            }
+            game.step(FLIGHT_TICKS);
+            game.end_turn();
+        }
+        game.snapshot()
+    }
+
+    #[test]
+    fn same_seed_plays_out_the_same() {
+        assert_eq!(play(11), play(11));
+    }
+
+    #[test]
+    fn different_seeds_play_out_differently() {
+        assert_ne!(play(11), play(12));
+    }
+}
@@ -0,0 +1,375 @@
+//! Buying things and putting them on the map.
+//!
+//! The number keys pick an item straight away, shift and the number keys the ones past the
+//! tenth, the shop menu does the same with the mouse.
+//! Whatever's picked follows the mouse until it's put down somewhere it fits.
+
+use std::collections::HashSet;
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::input::TickInput;
+use crate::{
+    ActivePlayer, Battlefield, Grid, Menu, Player, PlayerSide, PlayerState, Purchasable,
+    spawn_purchasable,
+};
+
+/// the number keys in the order they're given to shop entries
+const SHOP_DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+    KeyCode::Digit0,
+];
+
+/// The keys that pick a shop or editor entry: a number key, with shift held past the tenth entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shortcut {
+    shift: bool,
+    digit: usize,
+}
+
+impl Shortcut {
+    /// for the entry at `index` of a list, like `Purchasable::iter`, `None` past the twentieth
+    pub fn for_entry(index: usize) -> Option<Shortcut> {
+        (index < 2 * SHOP_DIGITS.len()).then_some(Shortcut {
+            shift: index >= SHOP_DIGITS.len(),
+            digit: index % SHOP_DIGITS.len(),
+        })
+    }
+
+    pub fn just_pressed(&self, keys: &ButtonInput<KeyCode>) -> bool {
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        shift == self.shift && keys.just_pressed(SHOP_DIGITS[self.digit])
+    }
+
+    pub fn label(&self) -> String {
+        let number = (self.digit + 1) % SHOP_DIGITS.len();
+        if self.shift {
+            format!("S+{number}")
+        } else {
+            number.to_string()
+        }
+    }
+}
+
+/// shortcuts pick a shop item straight away, with or without the shop open
+pub fn shop_shortcuts(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    active: Res<ActivePlayer>,
+    mut players: Query<&mut Player>,
+    menus: Query<Entity, With<Menu>>,
+) {
+    let Ok(mut player) = players.get_mut(**active) else {
+        return;
+    };
+    if !matches!(
+        player.state,
+        PlayerState::WaitingForAction | PlayerState::PurchaseMenu | PlayerState::Placing { .. }
+    ) {
+        return;
+    }
+    let Some(item) = Purchasable::iter().enumerate().find_map(|(index, item)| {
+        Shortcut::for_entry(index)
+            .is_some_and(|shortcut| shortcut.just_pressed(&input.keys))
+            .then_some(item)
+    }) else {
+        return;
+    };
+    if check_funds(item.cost(), player.money).is_err() {
+        return;
+    }
+    player.state = PlayerState::Placing { item };
+    for e in &menus {
+        commands.entity(e).despawn();
+    }
+}
+
+pub fn place_purchase(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    input: Res<TickInput>,
+    battlefield: Res<Battlefield>,
+    grid_query: Query<&Grid>,
+    active: Res<ActivePlayer>,
+    mut player_query: Query<&mut Player>,
+) {
+    if !input.mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Ok(mut player) = player_query.get_mut(**active) else {
+        return;
+    };
+
+    if let Some(grid_position) = input.cell(&battlefield) {
+        if let PlayerState::Placing { item } = player.state {
+            // the ghost already shows why it doesn't fit
+            let _ = buy(
+                &mut commands,
+                &asset_server,
+                &battlefield,
+                &occupied_cells(&grid_query),
+                &mut player,
+                item,
+                grid_position,
+            );
+        }
+    }
+}
+
+/// pay for `item` and put it at `grid_position` if there's space for everything it covers
+pub fn buy(
+    commands: &mut Commands,
+    asset_server: &Res<AssetServer>,
+    battlefield: &Battlefield,
+    occupied: &HashSet<I64Vec2>,
+    player: &mut Player,
+    item: Purchasable,
+    grid_position: I64Vec2,
+) -> Result<(), PlacementError> {
+    check_placement(
+        occupied,
+        battlefield,
+        item,
+        grid_position,
+        player.side,
+        player.money,
+    )?;
+    spawn_purchasable(
+        commands,
+        asset_server,
+        battlefield,
+        item,
+        player.side,
+        grid_position,
+    );
+    player.money -= item.cost();
+    player.state = PlayerState::WaitingForAction;
+    Ok(())
+}
+
+/// Why an item can't be bought or can't go where the player is pointing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementError {
+    OutOfBounds,
+    Occupied,
+    WrongSide,
+    NotSupported,
+    InsufficientFunds,
+}
+
+impl PlacementError {
+    pub fn reason(&self) -> &'static str {
+        match self {
+            PlacementError::OutOfBounds => "Out of bounds",
+            PlacementError::Occupied => "Something is already here",
+            PlacementError::WrongSide => "You can only build on your side",
+            PlacementError::NotSupported => "Needs something underneath",
+            PlacementError::InsufficientFunds => "Not enough money",
+        }
+    }
+}
+
+/// every grid cell something is standing on
+pub fn occupied_cells(grid_query: &Query<&Grid>) -> HashSet<I64Vec2> {
+    grid_query
+        .iter()
+        .flat_map(|grid| grid.positions.iter().copied())
+        .collect()
+}
+
+pub fn check_funds(cost: u32, money: u32) -> Result<(), PlacementError> {
+    if money < cost {
+        return Err(PlacementError::InsufficientFunds);
+    }
+    Ok(())
+}
+
+/// check the player can afford the item and that it fits on the map, on that player's
+/// side, on top of something and without overlapping anything else
+pub fn check_placement(
+    occupied: &HashSet<I64Vec2>,
+    battlefield: &Battlefield,
+    item: Purchasable,
+    grid_position: I64Vec2,
+    player_side: PlayerSide,
+    money: u32,
+) -> Result<(), PlacementError> {
+    check_funds(item.cost(), money)?;
+    check_site(occupied, battlefield, item, grid_position, player_side)
+}
+
+/// everything `check_placement` checks apart from the price
+pub fn check_site(
+    occupied: &HashSet<I64Vec2>,
+    battlefield: &Battlefield,
+    item: Purchasable,
+    grid_position: I64Vec2,
+    player_side: PlayerSide,
+) -> Result<(), PlacementError> {
+    let cells: Vec<I64Vec2> = item
+        .footprint()
+        .for_side(player_side)
+        .cells(grid_position)
+        .collect();
+
+    if !cells.iter().all(|cell| battlefield.contains(*cell)) {
+        return Err(PlacementError::OutOfBounds);
+    }
+
+    // check if on the correct side
+    if cells
+        .iter()
+        .any(|cell| battlefield.side_of(*cell) != player_side)
+    {
+        return Err(PlacementError::WrongSide);
+    }
+
+    if cells.iter().any(|cell| occupied.contains(cell)) {
+        return Err(PlacementError::Occupied);
+    }
+
+    // a cell facing that player's edge needs the map edge or something under it
+    let supported = cells
+        .iter()
+        .map(|cell| *cell + player_side.down())
+        .filter(|below| !cells.contains(below))
+        .any(|below| !battlefield.contains(below) || occupied.contains(&below));
+    if !supported {
+        return Err(PlacementError::NotSupported);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::STARTING_MONEY;
+    use crate::launcher::LauncherKind;
+
+    const CANNON: Purchasable = Purchasable::Launcher(LauncherKind::Cannon);
+
+    #[test]
+    fn every_shop_entry_has_its_own_shortcut() {
+        let shortcuts: Vec<Shortcut> = (0..Purchasable::iter().count())
+            .map(|index| Shortcut::for_entry(index).expect("every entry needs a shortcut"))
+            .collect();
+        let labels: HashSet<String> = shortcuts.iter().map(Shortcut::label).collect();
+        assert_eq!(labels.len(), shortcuts.len());
+    }
+
+    #[test]
+    fn shift_picks_the_entries_past_the_tenth() {
+        let mut keys = ButtonInput::<KeyCode>::default();
+        keys.press(KeyCode::Digit2);
+        assert!(Shortcut::for_entry(1).unwrap().just_pressed(&keys));
+        assert!(!Shortcut::for_entry(11).unwrap().just_pressed(&keys));
+
+        keys.press(KeyCode::ShiftLeft);
+        assert!(!Shortcut::for_entry(1).unwrap().just_pressed(&keys));
+        assert!(Shortcut::for_entry(11).unwrap().just_pressed(&keys));
+        assert_eq!(Shortcut::for_entry(11).unwrap().label(), "S+2");
+    }
+
+    const BATTLEFIELD: Battlefield = Battlefield {
+        width: 10,
+        height: 20,
+        divider: 10,
+    };
+
+    /// bottom player's ground is rows 0..=2, top player's is rows 17..=19
+    fn ground() -> HashSet<I64Vec2> {
+        (0..BATTLEFIELD.width as i64)
+            .flat_map(|x| [0, 1, 2, 17, 18, 19].map(|y| I64Vec2::new(x, y)))
+            .collect()
+    }
+
+    fn place(item: Purchasable, x: i64, y: i64, side: PlayerSide) -> Result<(), PlacementError> {
+        check_placement(
+            &ground(),
+            &BATTLEFIELD,
+            item,
+            I64Vec2::new(x, y),
+            side,
+            STARTING_MONEY,
+        )
+    }
+
+    #[test]
+    fn cannon_on_the_ground_is_valid() {
+        assert_eq!(place(CANNON, 4, 3, PlayerSide::Bottom), Ok(()));
+        assert_eq!(place(CANNON, 4, 15, PlayerSide::Top), Ok(()));
+    }
+
+    #[test]
+    fn cannon_hanging_off_the_edge_is_out_of_bounds() {
+        let right_edge = BATTLEFIELD.width as i64 - 1;
+        assert_eq!(
+            place(CANNON, right_edge, 3, PlayerSide::Bottom),
+            Err(PlacementError::OutOfBounds)
+        );
+        assert_eq!(
+            place(CANNON, -1, 3, PlayerSide::Bottom),
+            Err(PlacementError::OutOfBounds)
+        );
+        assert_eq!(
+            place(CANNON, 4, 19, PlayerSide::Top),
+            Err(PlacementError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn overlapping_is_occupied() {
+        assert_eq!(
+            place(CANNON, 4, 2, PlayerSide::Bottom),
+            Err(PlacementError::Occupied)
+        );
+    }
+
+    #[test]
+    fn crossing_the_divider_is_wrong_side() {
+        assert_eq!(
+            place(CANNON, 4, 9, PlayerSide::Bottom),
+            Err(PlacementError::WrongSide)
+        );
+        assert_eq!(
+            place(CANNON, 4, 3, PlayerSide::Top),
+            Err(PlacementError::WrongSide)
+        );
+    }
+
+    #[test]
+    fn floating_is_not_supported() {
+        assert_eq!(
+            place(Purchasable::Board, 4, 5, PlayerSide::Bottom),
+            Err(PlacementError::NotSupported)
+        );
+        // the top player's "down" is towards the top edge
+        assert_eq!(
+            place(Purchasable::Board, 4, 14, PlayerSide::Top),
+            Err(PlacementError::NotSupported)
+        );
+    }
+
+    #[test]
+    fn too_expensive_is_insufficient_funds() {
+        let result = check_placement(
+            &ground(),
+            &BATTLEFIELD,
+            CANNON,
+            I64Vec2::new(4, 3),
+            PlayerSide::Bottom,
+            LauncherKind::Cannon.cost() - 1,
+        );
+        assert_eq!(result, Err(PlacementError::InsufficientFunds));
+    }
+}
@@ -0,0 +1,337 @@
+//! Selling, repairing and moving a player's own structures.
+//!
+//! Right click something you own to open its menu. Every action uses up one of the
+//! player's actions for the turn, moving only counts once the structure is put down.
+
+use std::collections::HashSet;
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::input::TickInput;
+use crate::map::spawn_menu_button;
+use crate::shop::{PlacementError, check_funds, check_site, occupied_cells};
+use crate::{
+    ActivePlayer, Battlefield, Board, Breakable, Grid, Player, PlayerSide, PlayerState, Purchasable,
+};
+
+/// share of the price given back when selling
+const SELL_REFUND_PERCENT: u32 = 50;
+/// share of the price charged to move something
+const MOVE_FEE_PERCENT: u32 = 25;
+
+/// Something a player owns that can be sold, repaired or moved.
+#[derive(Component)]
+pub struct Structure {
+    pub item: Purchasable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StructureAction {
+    Sell,
+    Repair,
+    Move,
+}
+
+/// Context menu for the structure that was right clicked.
+#[derive(Component)]
+pub struct StructureMenu {
+    target: Entity,
+}
+
+#[derive(Component)]
+pub struct StructureActionButton {
+    pub action: StructureAction,
+}
+
+/// half the price, scaled down by how much health is missing, `None` for things that
+/// were never for sale
+fn sell_refund(item: Purchasable, breakable: Option<&Breakable>) -> Option<u32> {
+    let refund = item.price()?.saturating_mul(SELL_REFUND_PERCENT) / 100;
+    Some(match breakable {
+        Some(breakable) => {
+            refund.saturating_mul(breakable.health as u32) / breakable.max_health.max(1) as u32
+        }
+        None => refund,
+    })
+}
+
+/// the item's price scaled by how much health is missing
+fn repair_cost(item: Purchasable, breakable: &Breakable) -> u32 {
+    let missing = (breakable.max_health - breakable.health) as u32;
+    item.cost()
+        .saturating_mul(missing)
+        .div_ceil(breakable.max_health as u32)
+}
+
+fn move_fee(item: Purchasable) -> u32 {
+    item.cost().saturating_mul(MOVE_FEE_PERCENT) / 100
+}
+
+/// like `check_placement` but charges the move fee instead of the price
+pub fn check_move(
+    occupied: &HashSet<I64Vec2>,
+    battlefield: &Battlefield,
+    item: Purchasable,
+    grid_position: I64Vec2,
+    player_side: PlayerSide,
+    money: u32,
+) -> Result<(), PlacementError> {
+    check_funds(move_fee(item), money)?;
+    check_site(occupied, battlefield, item, grid_position, player_side)
+}
+
+/// every occupied cell apart from the ones the moving structure is on
+pub fn occupied_without(grid_query: &Query<&Grid>, entity: Entity) -> HashSet<I64Vec2> {
+    let mut occupied = occupied_cells(grid_query);
+    if let Ok(grid) = grid_query.get(entity) {
+        for position in &grid.positions {
+            occupied.remove(position);
+        }
+    }
+    occupied
+}
+
+pub fn open_structure_menu(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    battlefield: Res<Battlefield>,
+    active: Res<ActivePlayer>,
+    players: Query<&Player>,
+    structures: Query<(Entity, &Structure, &Board, &Grid, Option<&Breakable>)>,
+    menus: Query<Entity, With<StructureMenu>>,
+) {
+    if !input.mouse.just_pressed(MouseButton::Right) {
+        return;
+    }
+    for e in &menus {
+        commands.entity(e).despawn();
+    }
+
+    let Ok(player) = players.get(**active) else {
+        return;
+    };
+    if player.state != PlayerState::WaitingForAction {
+        return;
+    }
+    let (Some(cursor), Some(grid_position)) = (input.cursor, input.cell(&battlefield)) else {
+        return;
+    };
+    let Some((target, structure, _, _, breakable)) =
+        structures.iter().find(|(_, _, board, grid, _)| {
+            board.player_side == player.side && grid.positions.contains(&grid_position)
+        })
+    else {
+        return;
+    };
+
+    let item = structure.item;
+    let name = item.to_string();
+    let mut actions = Vec::new();
+    if let Some(refund) = sell_refund(item, breakable) {
+        actions.push((StructureAction::Sell, format!("Sell +${refund}")));
+    }
+    if let Some(breakable) = breakable.filter(|b| b.health < b.max_health) {
+        let cost = repair_cost(item, breakable);
+        if player.money >= cost {
+            actions.push((StructureAction::Repair, format!("Repair ${cost}")));
+        }
+    }
+    let fee = move_fee(item);
+    if player.money >= fee {
+        actions.push((StructureAction::Move, format!("Move ${fee}")));
+    }
+
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                left: Val::Px(cursor.x),
+                top: Val::Px(cursor.y),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                ..default()
+            },
+            BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+            StructureMenu { target },
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                Text::new(format!("{name} ({} actions left)", player.actions)),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+            ));
+            if player.actions == 0 {
+                return;
+            }
+            for (action, label) in actions {
+                spawn_menu_button(parent, label, 140.0, StructureActionButton { action });
+            }
+        });
+}
+
+pub fn structure_actions(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    buttons: Query<&StructureActionButton>,
+    menu: Query<(Entity, &StructureMenu)>,
+    active: Res<ActivePlayer>,
+    mut players: Query<&mut Player>,
+    mut structures: Query<(&Structure, &Board, Option<&mut Breakable>)>,
+) {
+    let Some(button) = input.clicked(&buttons) else {
+        return;
+    };
+    let Ok((menu_e, menu)) = menu.single() else {
+        return;
+    };
+    commands.entity(menu_e).despawn();
+
+    let Ok(mut player) = players.get_mut(**active) else {
+        return;
+    };
+    if player.state != PlayerState::WaitingForAction {
+        return;
+    }
+    let Ok((structure, board, breakable)) = structures.get_mut(menu.target) else {
+        return;
+    };
+    // only ever the player's own, even if the menu was opened by the other one
+    if board.player_side != player.side {
+        return;
+    }
+    if player.actions == 0 {
+        return;
+    }
+
+    let item = structure.item;
+    match button.action {
+        StructureAction::Sell => {
+            let Some(refund) = sell_refund(item, breakable.as_deref()) else {
+                return;
+            };
+            player.money += refund;
+            player.actions -= 1;
+            commands.entity(menu.target).despawn();
+        }
+        StructureAction::Repair => {
+            let Some(mut breakable) = breakable else {
+                return;
+            };
+            let cost = repair_cost(item, &breakable);
+            if player.money >= cost {
+                player.money -= cost;
+                player.actions -= 1;
+                breakable.health = breakable.max_health;
+            }
+        }
+        StructureAction::Move => {
+            if player.money >= move_fee(item) {
+                player.state = PlayerState::Moving {
+                    entity: menu.target,
+                    item,
+                };
+            }
+        }
+    }
+}
+
+/// put the structure being moved down where the player clicks, right click or escape cancels
+pub fn place_move(
+    input: Res<TickInput>,
+    battlefield: Res<Battlefield>,
+    mut grids: Query<(Entity, &mut Grid, &mut Transform)>,
+    active: Res<ActivePlayer>,
+    mut players: Query<&mut Player>,
+) {
+    let Ok(mut player) = players.get_mut(**active) else {
+        return;
+    };
+    let PlayerState::Moving { entity, item } = player.state else {
+        return;
+    };
+    // knocked down before it could be moved
+    if !grids.contains(entity)
+        || input.mouse.just_pressed(MouseButton::Right)
+        || input.keys.just_pressed(KeyCode::Escape)
+    {
+        player.state = PlayerState::WaitingForAction;
+        return;
+    }
+    if !input.mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    let Some(grid_position) = input.cell(&battlefield) else {
+        return;
+    };
+    let occupied: HashSet<I64Vec2> = grids
+        .iter()
+        .filter(|(e, ..)| *e != entity)
+        .flat_map(|(_, grid, _)| grid.positions.clone())
+        .collect();
+    if check_move(
+        &occupied,
+        &battlefield,
+        item,
+        grid_position,
+        player.side,
+        player.money,
+    )
+    .is_err()
+    {
+        return;
+    }
+
+    let Ok((_, mut grid, mut transform)) = grids.get_mut(entity) else {
+        return;
+    };
+    let footprint = item.footprint().for_side(player.side);
+    *grid = footprint.grid(grid_position);
+    transform.translation = footprint
+        .center(&battlefield, grid_position)
+        .extend(transform.translation.z);
+    player.money -= move_fee(item);
+    player.actions -= 1;
+    player.state = PlayerState::WaitingForAction;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SingleBlockType;
+    use crate::launcher::LauncherKind;
+
+    const CANNON: Purchasable = Purchasable::Launcher(LauncherKind::Cannon);
+
+    #[test]
+    fn selling_refunds_half_the_price_at_full_health() {
+        let breakable = Breakable::new(4);
+        assert_eq!(
+            sell_refund(CANNON, Some(&breakable)),
+            Some(CANNON.cost() / 2)
+        );
+    }
+
+    #[test]
+    fn selling_a_damaged_structure_refunds_less() {
+        let mut breakable = Breakable::new(4);
+        breakable.health = 1;
+        assert_eq!(
+            sell_refund(CANNON, Some(&breakable)),
+            Some(CANNON.cost() / 2 / 4)
+        );
+        breakable.health = 0;
+        assert_eq!(sell_refund(CANNON, Some(&breakable)), Some(0));
+    }
+
+    #[test]
+    fn blocks_that_were_never_for_sale_cant_be_sold() {
+        let bedrock = Purchasable::Block(SingleBlockType::Bedrock);
+        assert_eq!(sell_refund(bedrock, Some(&Breakable::new(4))), None);
+        assert_eq!(sell_refund(bedrock, None), None);
+    }
+}
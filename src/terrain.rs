@@ -0,0 +1,124 @@
+//! The map: picking one, editing one and putting the picked one on the battlefield.
+
+use std::collections::HashSet;
+
+use bevy::{math::I64Vec2, prelude::*};
+
+use crate::{
+    AppState, Background, Battlefield, Block, Board, GameSet, Grid, SelectedMap, editor,
+    from_grid_coords, map, spawn_block, spawn_purchasable,
+};
+
+const BACKGROUND_STARTING_POSITION: Vec3 = Vec3::new(0.0, 0.0, -10.0);
+
+pub struct TerrainPlugin;
+
+impl Plugin for TerrainPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(AppState::MapSelect),
+            (map::discover_maps, map::spawn_map_select).chain(),
+        )
+        .add_systems(OnExit(AppState::MapSelect), map::despawn_map_select)
+        .add_systems(
+            OnEnter(AppState::Editor),
+            (spawn_background, editor::spawn_editor_ui),
+        )
+        .add_systems(OnExit(AppState::Editor), editor::despawn_editor)
+        .add_systems(OnEnter(AppState::InGame), (spawn_map, spawn_background))
+        .add_systems(
+            Update,
+            (
+                (map::choose_map, map::open_editor).run_if(in_state(AppState::MapSelect)),
+                (
+                    editor::choose_tool,
+                    editor::paint,
+                    editor::editor_shortcuts,
+                    editor::rebuild_editor_view,
+                    editor::editor_status_text,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::Editor)),
+            ),
+        )
+        .add_systems(FixedUpdate, settle_blocks.in_set(GameSet::Simulation));
+    }
+}
+
+fn spawn_background(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    battlefield: Res<Battlefield>,
+) {
+    let background_texture = asset_server.load("sky.png");
+    commands.spawn((
+        Sprite {
+            image: background_texture,
+            custom_size: Some(battlefield.size()),
+            ..default()
+        },
+        Transform {
+            translation: BACKGROUND_STARTING_POSITION,
+            ..default()
+        },
+        Background,
+    ));
+}
+
+fn spawn_map(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    battlefield: Res<Battlefield>,
+    map: Res<SelectedMap>,
+) {
+    for (grid_position, block_type) in map.0.blocks() {
+        spawn_block(
+            &mut commands,
+            &asset_server,
+            &battlefield,
+            block_type,
+            grid_position,
+            battlefield.side_of(grid_position),
+        );
+    }
+    for structure in &map.0.structures {
+        spawn_purchasable(
+            &mut commands,
+            &asset_server,
+            &battlefield,
+            structure.kind,
+            structure.side,
+            I64Vec2::new(structure.position.0, structure.position.1),
+        );
+    }
+}
+
+/// drop loose blocks one cell per tick until they land on something
+fn settle_blocks(
+    battlefield: Res<Battlefield>,
+    mut blocks: Query<(&Block, &Board, &mut Grid, &mut Transform)>,
+    others: Query<&Grid, Without<Block>>,
+) {
+    let mut occupied: HashSet<I64Vec2> = others
+        .iter()
+        .chain(blocks.iter().map(|(_, _, grid, _)| grid))
+        .flat_map(|grid| grid.positions.iter().copied())
+        .collect();
+
+    for (block, board, mut grid, mut transform) in &mut blocks {
+        if !block.block_type.falls() {
+            continue;
+        }
+        let position = grid.positions[0];
+        let below = position + board.player_side.down();
+        let on_map = below.y >= 0 && below.y < battlefield.height as i64;
+        if !on_map || occupied.contains(&below) {
+            continue;
+        }
+        occupied.remove(&position);
+        occupied.insert(below);
+        grid.positions[0] = below;
+        transform.translation =
+            from_grid_coords(&battlefield, below).extend(transform.translation.z);
+    }
+}
@@ -0,0 +1,180 @@
+//! Whose turn it is, and starting and ending matches and turns.
+//!
+//! Every other plugin builds on this one: it owns the app state, the players, the dice, the
+//! input and the order the `GameSet`s run in.
+
+use bevy::input::InputSystem;
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+
+use crate::aim::{self, FineAim};
+use crate::combat;
+use crate::economy::IncomeBreakdown;
+use crate::input::{TickInput, buffer_input, clear_tick_input};
+use crate::launcher::Launcher;
+use crate::rng::GameRng;
+use crate::shop;
+use crate::structures::{self, StructureMenu};
+use crate::{
+    ActivePlayer, AppState, EndTurn, GameSet, Menu, Player, PlayerSide, PlayerState,
+    STARTING_MONEY, SelectedMap, Turn, TurnStarted,
+};
+
+/// selling, repairing or moving something each use one up
+const ACTIONS_PER_TURN: u32 = 2;
+
+pub struct TurnPlugin;
+
+impl Plugin for TurnPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>()
+            .add_event::<EndTurn>()
+            .add_event::<TurnStarted>()
+            .init_resource::<GameRng>()
+            .init_resource::<TickInput>()
+            .configure_sets(
+                PreUpdate,
+                GameSet::Input
+                    .after(InputSystem)
+                    .after(UiSystem::Focus)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .configure_sets(
+                FixedUpdate,
+                (
+                    GameSet::Commands,
+                    GameSet::Simulation,
+                    GameSet::Resolution,
+                    GameSet::Presentation,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(OnEnter(AppState::InGame), (start_match, spawn_players))
+            .add_systems(PreUpdate, buffer_input.in_set(GameSet::Input))
+            .add_systems(FixedPostUpdate, clear_tick_input)
+            .add_systems(
+                FixedUpdate,
+                (
+                    turn_done,
+                    // everything the player did this tick is theirs, not the next player's
+                    change_turn
+                        .after(turn_done)
+                        .after(combat::fire_selected_launcher)
+                        .after(aim::fine_aim)
+                        .after(shop::place_purchase)
+                        .after(shop::shop_shortcuts)
+                        .after(structures::structure_actions)
+                        .after(structures::place_move),
+                )
+                    .in_set(GameSet::Commands),
+            );
+    }
+}
+
+fn spawn_players(mut commands: Commands) {
+    commands.spawn(Player {
+        side: PlayerSide::Top,
+        money: STARTING_MONEY,
+        actions: ACTIONS_PER_TURN,
+        damage_taken: 0,
+        bounty: 0,
+        last_income: IncomeBreakdown::default(),
+        state: PlayerState::WaitingForTurn,
+    });
+    let bottom = commands
+        .spawn(Player {
+            side: PlayerSide::Bottom,
+            money: STARTING_MONEY,
+            actions: ACTIONS_PER_TURN,
+            damage_taken: 0,
+            bounty: 0,
+            last_income: IncomeBreakdown::default(),
+            state: PlayerState::WaitingForAction,
+        })
+        .id();
+    commands.insert_resource(ActivePlayer(bottom));
+    commands.spawn(Turn {
+        player_side: PlayerSide::Bottom,
+        number: 1,
+    });
+}
+
+/// What a match was started with, enough to play it again with the same commands.
+#[derive(Resource, Debug, Clone)]
+pub struct MatchInfo {
+    pub map: String,
+    pub seed: u64,
+}
+
+/// record the match and start the dice over from its seed
+fn start_match(mut commands: Commands, map: Res<SelectedMap>, mut rng: ResMut<GameRng>) {
+    rng.restart();
+    let info = MatchInfo {
+        map: map.0.name.clone(),
+        seed: rng.seed(),
+    };
+    info!("starting {} with seed {}", info.map, info.seed);
+    commands.insert_resource(info);
+}
+
+/// the shop and the structure menus alike
+type AnyMenu = Or<(With<Menu>, With<StructureMenu>)>;
+
+#[allow(clippy::too_many_arguments)]
+fn change_turn(
+    mut commands: Commands,
+    mut events: EventReader<EndTurn>,
+    mut turn: Single<&mut Turn>,
+    mut players: Query<(Entity, &mut Player)>,
+    mut active: ResMut<ActivePlayer>,
+    mut launchers: Query<(Entity, &mut Launcher)>,
+    menus: Query<Entity, AnyMenu>,
+    mut started: EventWriter<TurnStarted>,
+) {
+    // Consume all EndTurn events this frame
+    let flips = events.read().count();
+    if flips % 2 == 0 {
+        // Even number of events cancels out; nothing to do.
+        return;
+    }
+
+    // Flip turn once
+    turn.player_side = turn.player_side.other();
+    turn.number += 1;
+
+    // Update players, they're paid once the economy sees the turn start
+    for (e, mut p) in &mut players {
+        if p.side == turn.player_side {
+            *active = ActivePlayer(e);
+            p.state = PlayerState::WaitingForAction;
+            p.actions = ACTIONS_PER_TURN;
+        } else {
+            p.state = PlayerState::WaitingForTurn;
+        }
+    }
+
+    // Clear selection and reload the new player's launchers
+    for (e, mut launcher) in &mut launchers {
+        launcher.is_selected = false;
+        commands.entity(e).remove::<FineAim>();
+        if launcher.player_side == turn.player_side {
+            launcher.reload = launcher.reload.saturating_sub(1);
+        }
+    }
+
+    // Close any open menus
+    for e in &menus {
+        commands.entity(e).despawn();
+    }
+
+    started.write(TurnStarted {
+        side: turn.player_side,
+    });
+}
+
+fn turn_done(input: Res<TickInput>, mut event_writer: EventWriter<EndTurn>) {
+    if input.keys.just_pressed(KeyCode::KeyD) {
+        event_writer.write(EndTurn);
+    }
+}
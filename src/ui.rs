@@ -0,0 +1,515 @@
+//! Menus, buttons and overlays: the shop, the placement ghost and the HUD.
+
+use bevy::prelude::*;
+
+use crate::input::TickInput;
+use crate::shop::{self, Shortcut, check_funds, check_placement, occupied_cells};
+use crate::{
+    ActivePlayer, AppState, Battlefield, GameSet, Grid, Menu, NORMAL_BUTTON, Player, PlayerState,
+    Purchasable, PurchaseButton, PurchaseMenuButton, hud, structures, to_grid_coords,
+};
+
+const SHOP_WIDTH: f32 = 230.0;
+const SHOP_ICON_SIZE: f32 = 24.0;
+const SHOP_TEXT_COLOR: Color = Color::srgb(0.9, 0.9, 0.9);
+const UNAFFORDABLE_COLOR: Color = Color::srgb(0.45, 0.45, 0.45);
+
+const GHOST_VALID_COLOR: Color = Color::srgba(0.4, 1.0, 0.4, 0.6);
+const GHOST_INVALID_COLOR: Color = Color::srgba(1.0, 0.3, 0.3, 0.6);
+const GHOST_Z: f32 = 2.0;
+const TOOLTIP_OFFSET: Vec2 = Vec2::new(16.0, 16.0);
+
+const HOVERED_BUTTON: Color = Color::srgb(0.25, 0.25, 0.25);
+const PRESSED_BUTTON: Color = Color::srgb(0.35, 0.75, 0.35);
+
+pub struct UiPlugin;
+
+impl Plugin for UiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(AppState::InGame), (spawn_ui, hud::spawn_hud))
+            .add_systems(
+                FixedUpdate,
+                (
+                    open_close_purchase_menu,
+                    // so the click on the shop button doesn't place the item too
+                    purchase.after(shop::place_purchase),
+                )
+                    .in_set(GameSet::Commands),
+            )
+            .add_systems(
+                FixedUpdate,
+                (
+                    open_close_purchase_menu_text,
+                    update_affordability,
+                    shop_tooltip,
+                    update_placement_ghost,
+                    hud::record_turn_history,
+                    hud::update_hud.after(hud::record_turn_history),
+                )
+                    .in_set(GameSet::Presentation),
+            )
+            .add_systems(Update, button_color);
+    }
+}
+
+fn spawn_ui(mut commands: Commands) {
+    // ── Purchase button in top-right, the HUD is on the left ────────────────────
+    commands
+        .spawn((
+            // container to place the button
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(15.0),
+                right: Val::Px(15.0),
+                width: Val::Px(150.0),
+                ..default()
+            },
+        ))
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    Button, // marker that makes this entity a button
+                    // size/border and centering for the inner text
+                    Node {
+                        width: Val::Percent(100.0),
+                        height: Val::Px(65.0),
+                        border: UiRect::all(Val::Px(5.0)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    BorderColor(Color::BLACK),
+                    BorderRadius::MAX,
+                    BackgroundColor(NORMAL_BUTTON),
+                    PurchaseMenuButton, // your marker
+                ))
+                .with_children(|p| {
+                    p.spawn((
+                        Text::new("Purchase"),
+                        TextFont {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                        TextColor(Color::srgb(0.9, 0.9, 0.9)),
+                        TextShadow::default(),
+                    ));
+                });
+        });
+}
+
+/// the text colour and icon tint of a shop entry, too expensive entries are greyed out
+fn entry_colors(item: Purchasable, money: u32) -> (Color, Color) {
+    if check_funds(item.cost(), money).is_ok() {
+        (SHOP_TEXT_COLOR, Color::WHITE)
+    } else {
+        (UNAFFORDABLE_COLOR, UNAFFORDABLE_COLOR)
+    }
+}
+
+/// grey the shop entries out again whenever the active player's money changes
+fn update_affordability(
+    active: Res<ActivePlayer>,
+    players: Query<&Player, Changed<Player>>,
+    entries: Query<(&PurchaseButton, &Children)>,
+    mut texts: Query<&mut TextColor>,
+    mut icons: Query<&mut ImageNode>,
+) {
+    let Ok(player) = players.get(**active) else {
+        return;
+    };
+    for (entry, children) in &entries {
+        let (color, tint) = entry_colors(entry.item, player.money);
+        for child in children {
+            if let Ok(mut text) = texts.get_mut(*child) {
+                text.0 = color;
+            }
+            if let Ok(mut icon) = icons.get_mut(*child) {
+                icon.color = tint;
+            }
+        }
+    }
+}
+
+/// Stats of the hovered shop entry.
+#[derive(Component)]
+struct ShopTooltip;
+
+// show purchasing ui when a player state changes to purchasing which shows all the items that can be purchased
+fn spawn_purchase_ui(commands: &mut Commands, assets: &AssetServer, money: u32) {
+    // Root panel (right side, below money indicator)
+    commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                top: Val::Px(100.0),
+                right: Val::Px(15.0),
+                width: Val::Px(SHOP_WIDTH),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|parent| {
+            for (index, purchasable) in Purchasable::iter().enumerate() {
+                let (color, tint) = entry_colors(purchasable, money);
+                let label = |text: String| {
+                    (
+                        Text::new(text),
+                        TextFont {
+                            font: assets.load("fonts/FiraSans-Bold.ttf"),
+                            font_size: 18.0,
+                            ..default()
+                        },
+                        TextColor(color),
+                        TextShadow::default(),
+                    )
+                };
+                parent
+                    .spawn((
+                        Button,
+                        Node {
+                            width: Val::Percent(100.0),
+                            height: Val::Px(36.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            padding: UiRect::horizontal(Val::Px(8.0)),
+                            column_gap: Val::Px(8.0),
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        BorderColor(Color::BLACK),
+                        BackgroundColor(NORMAL_BUTTON),
+                        BorderRadius::MAX, // optional: rounded corners
+                        PurchaseButton { item: purchasable },
+                    ))
+                    .with_children(|btn| {
+                        let shortcut = Shortcut::for_entry(index).map(|s| s.label());
+                        btn.spawn(label(shortcut.unwrap_or_default()));
+                        btn.spawn((
+                            ImageNode {
+                                image: purchasable.image(assets),
+                                color: tint,
+                                ..default()
+                            },
+                            Node {
+                                width: Val::Px(SHOP_ICON_SIZE),
+                                height: Val::Px(SHOP_ICON_SIZE),
+                                ..default()
+                            },
+                        ));
+                        btn.spawn(label(purchasable.to_string())).insert(Node {
+                            flex_grow: 1.0,
+                            ..default()
+                        });
+                        btn.spawn(label(format!("${}", purchasable.cost())));
+                    });
+            }
+        });
+}
+
+fn purchase(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    entries: Query<&PurchaseButton>,
+    active: Res<ActivePlayer>,
+    mut players: Query<&mut Player>,
+    menus: Query<Entity, With<Menu>>,
+) {
+    let Ok(mut player) = players.get_mut(**active) else {
+        return;
+    };
+
+    // Find the first button that was pressed AND is affordable; grab the item.
+    if let Some(item) = input
+        .clicked
+        .iter()
+        .filter_map(|button| entries.get(*button).ok())
+        .find(|entry| check_funds(entry.item.cost(), player.money).is_ok())
+        .map(|entry| entry.item)
+    {
+        player.state = PlayerState::Placing { item };
+        for e in &menus {
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+fn open_close_purchase_menu(
+    mut commands: Commands,
+    input: Res<TickInput>,
+    shop_buttons: Query<&PurchaseMenuButton>,
+    active: Res<ActivePlayer>,
+    mut players: Query<&mut Player>,
+    menus: Query<Entity, With<Menu>>,
+    assets: Res<AssetServer>, // pass through to your spawner
+) {
+    // Only act if the shop button was *pressed* since the last tick.
+    if input.clicked(&shop_buttons).is_none() {
+        return;
+    }
+
+    let Ok(mut player) = players.get_mut(**active) else {
+        return;
+    };
+
+    match player.state {
+        PlayerState::WaitingForAction => {
+            player.state = PlayerState::PurchaseMenu;
+            // Your spawner signature likely: fn spawn_purchase_ui(commands: &mut Commands, assets: &AssetServer)
+            spawn_purchase_ui(&mut commands, &assets, player.money);
+        }
+        PlayerState::PurchaseMenu => {
+            player.state = PlayerState::WaitingForAction;
+            for e in &menus {
+                commands.entity(e).despawn();
+            }
+        }
+        _ => {}
+    }
+}
+
+/// show the stats of the shop entry under the mouse, and why it can't be bought
+fn shop_tooltip(
+    mut commands: Commands,
+    window: Single<&Window>,
+    entries: Query<(&Interaction, &PurchaseButton)>,
+    active: Res<ActivePlayer>,
+    players: Query<&Player>,
+    mut tooltip_q: Query<(Entity, &mut Node, &mut Text), With<ShopTooltip>>,
+) {
+    let hovered = entries
+        .iter()
+        .find_map(|(interaction, entry)| (*interaction != Interaction::None).then_some(entry.item));
+    let shopping = players
+        .get(**active)
+        .ok()
+        .filter(|p| p.state == PlayerState::PurchaseMenu);
+    let (Some(item), Some(player), Some(cursor)) = (hovered, shopping, window.cursor_position())
+    else {
+        for (e, ..) in &tooltip_q {
+            commands.entity(e).despawn();
+        }
+        return;
+    };
+
+    let mut text = item.describe();
+    if let Err(err) = check_funds(item.cost(), player.money) {
+        text.push('\n');
+        text.push_str(err.reason());
+    }
+    // to the left of the cursor, the shop is against the right edge
+    let right = Val::Px(window.width() - cursor.x + TOOLTIP_OFFSET.x);
+    let top = Val::Px(cursor.y + TOOLTIP_OFFSET.y);
+    match tooltip_q.single_mut() {
+        Ok((_, mut node, mut tooltip)) => {
+            node.right = right;
+            node.top = top;
+            tooltip.0 = text;
+        }
+        Err(_) => {
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    right,
+                    top,
+                    max_width: Val::Px(SHOP_WIDTH),
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                Text::new(text),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                ShopTooltip,
+            ));
+        }
+    }
+}
+
+fn button_color(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut bg_color) in &mut interaction_query {
+        match *interaction {
+            Interaction::Pressed => {
+                bg_color.0 = PRESSED_BUTTON;
+            }
+            Interaction::Hovered => {
+                bg_color.0 = HOVERED_BUTTON;
+            }
+            Interaction::None => {
+                bg_color.0 = NORMAL_BUTTON;
+            }
+        }
+    }
+}
+
+fn open_close_purchase_menu_text(
+    buttons: Query<&Children, (With<Button>, With<PurchaseMenuButton>)>,
+    active: Res<ActivePlayer>,
+    players: Query<&Player>,
+    children_q: Query<&Children>,
+    mut spans: Query<&mut TextSpan>,
+) {
+    let Ok(player) = players.get(**active) else {
+        return;
+    };
+
+    let label = match player.state {
+        PlayerState::WaitingForAction => "Purchase",
+        PlayerState::PurchaseMenu => "Cancel",
+        _ => "Purchase",
+    };
+
+    // Iterate Entities directly (no &)
+    for btn_children in &buttons {
+        for child in btn_children.iter() {
+            // child: Entity
+            if let Ok(text_children) = children_q.get(child) {
+                for span_ent in text_children.iter() {
+                    // span_ent: Entity
+                    if let Ok(mut span) = spans.get_mut(span_ent) {
+                        span.0 = label.to_string();
+                        return;
+                    }
+                }
+            }
+            // Fallback if a TextSpan is on the button entity itself
+            if let Ok(mut span) = spans.get_mut(child) {
+                span.0 = label.to_string();
+                return;
+            }
+        }
+    }
+}
+
+/// Translucent copy of the item being placed that follows the mouse.
+#[derive(Component)]
+struct PlacementGhost {
+    item: Purchasable,
+}
+
+/// Says why the ghost is red.
+#[derive(Component)]
+struct PlacementTooltip;
+
+#[allow(clippy::too_many_arguments)]
+fn update_placement_ghost(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    input: Res<TickInput>,
+    battlefield: Res<Battlefield>,
+    grid_query: Query<&Grid>,
+    active: Res<ActivePlayer>,
+    players: Query<&Player>,
+    mut ghost_q: Query<(Entity, &PlacementGhost, &mut Sprite, &mut Transform)>,
+    mut tooltip_q: Query<(Entity, &mut Node, &mut Text), With<PlacementTooltip>>,
+) {
+    let placing = players.get(**active).ok().and_then(|p| match p.state {
+        PlayerState::Placing { item } => Some((p, item, None)),
+        PlayerState::Moving { entity, item } => Some((p, item, Some(entity))),
+        _ => None,
+    });
+    let (Some((player, item, moving)), Some(cursor), Some(world_position)) =
+        (placing, input.cursor, input.world_cursor)
+    else {
+        for (e, ..) in &ghost_q {
+            commands.entity(e).despawn();
+        }
+        for (e, ..) in &tooltip_q {
+            commands.entity(e).despawn();
+        }
+        return;
+    };
+
+    let footprint = item.footprint().for_side(player.side);
+    let grid_position = to_grid_coords(&battlefield, world_position);
+    let placement = match moving {
+        Some(entity) => structures::check_move(
+            &structures::occupied_without(&grid_query, entity),
+            &battlefield,
+            item,
+            grid_position,
+            player.side,
+            player.money,
+        ),
+        None => check_placement(
+            &occupied_cells(&grid_query),
+            &battlefield,
+            item,
+            grid_position,
+            player.side,
+            player.money,
+        ),
+    };
+    let color = if placement.is_ok() {
+        GHOST_VALID_COLOR
+    } else {
+        GHOST_INVALID_COLOR
+    };
+    let translation = footprint
+        .center(&battlefield, grid_position)
+        .extend(GHOST_Z);
+
+    match ghost_q.single_mut() {
+        Ok((_, ghost, mut sprite, mut transform)) if ghost.item == item => {
+            sprite.color = color;
+            transform.translation = translation;
+        }
+        existing => {
+            if let Ok((e, ..)) = existing {
+                commands.entity(e).despawn();
+            }
+            commands.spawn((
+                Sprite {
+                    image: item.image(&asset_server),
+                    color,
+                    flip_y: player.side.flip_y(),
+                    custom_size: Some(footprint.world_size()),
+                    ..default()
+                },
+                Transform::from_translation(translation),
+                PlacementGhost { item },
+            ));
+        }
+    }
+
+    let tooltip_position = cursor + TOOLTIP_OFFSET;
+    match (placement, tooltip_q.single_mut()) {
+        (Ok(()), tooltip) => {
+            if let Ok((e, ..)) = tooltip {
+                commands.entity(e).despawn();
+            }
+        }
+        (Err(err), Ok((_, mut node, mut text))) => {
+            node.left = Val::Px(tooltip_position.x);
+            node.top = Val::Px(tooltip_position.y);
+            text.0 = err.reason().to_string();
+        }
+        (Err(err), Err(_)) => {
+            commands.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(tooltip_position.x),
+                    top: Val::Px(tooltip_position.y),
+                    padding: UiRect::all(Val::Px(4.0)),
+                    ..default()
+                },
+                BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+                Text::new(err.reason()),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                TextColor(Color::WHITE),
+                PlacementTooltip,
+            ));
+        }
+    }
+}
@@ -0,0 +1,268 @@
+//! Weather rolled at the start of every turn.
+//!
+//! Wind pushes shots sideways for the whole turn, rain puts out fires and softens the
+//! dirt, lightning strikes one of the tallest structures and meteor showers rain rocks
+//! on both sides. Start the game with `--no-weather` to keep the sky clear.
+
+use bevy::{math::I64Vec2, prelude::*};
+use rand::Rng;
+
+use crate::combat::{BreakableTarget, DamageEvents, damage_target};
+use crate::fire::{Burning, catch_fire};
+use crate::fixed;
+use crate::launcher::{beam, projectile};
+use crate::rng::{GameRng, RngStream};
+use crate::{
+    AmmoType, Battlefield, Block, Breakable, CannonBall, Collider, PlayerSide, SingleBlockType,
+    TurnStarted, Velocity, from_grid_coords,
+};
+
+/// chances out of 100 for each kind of weather
+const WIND_CHANCE: u32 = 25;
+const RAIN_CHANCE: u32 = 15;
+const LIGHTNING_CHANCE: u32 = 10;
+const METEOR_CHANCE: u32 = 10;
+/// sideways push on every shot each tick, in sub-cells
+const MIN_WIND: i64 = 5;
+const MAX_WIND: i64 = 20;
+const LIGHTNING_DAMAGE: u8 = 2;
+/// how many of the tallest structures lightning picks from
+const LIGHTNING_CANDIDATES: usize = 3;
+const LIGHTNING_COLOR: Color = Color::srgb(0.95, 0.95, 0.6);
+const METEORS_PER_SIDE: usize = 3;
+const METEOR_SPEED: i64 = 6 * fixed::PER_PIXEL;
+/// most sideways speed a meteor falls with
+const METEOR_DRIFT: i64 = 2 * fixed::PER_PIXEL;
+const METEOR_COLOR: Color = Color::srgb(0.6, 0.25, 0.15);
+
+/// Whether weather happens at all.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeatherSettings {
+    pub enabled: bool,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        WeatherSettings { enabled: true }
+    }
+}
+
+impl WeatherSettings {
+    /// weather is on unless `args` has `--no-weather`
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> WeatherSettings {
+        WeatherSettings {
+            enabled: !args.into_iter().any(|arg| arg == "--no-weather"),
+        }
+    }
+}
+
+/// This turn's weather.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Weather {
+    #[default]
+    Clear,
+    /// pushes every shot along x by `force` each tick
+    Wind { force: i64 },
+    /// puts out fires and softens dirt
+    Rain,
+    /// strikes one of the tallest structures
+    Lightning,
+    /// rocks fall on both sides
+    Meteors,
+}
+
+impl Weather {
+    fn roll(rng: &mut impl Rng) -> Weather {
+        let roll = rng.gen_range(0..100);
+        if roll < WIND_CHANCE {
+            let force = rng.gen_range(MIN_WIND..=MAX_WIND);
+            let force = if rng.r#gen() { force } else { -force };
+            Weather::Wind { force }
+        } else if roll < WIND_CHANCE + RAIN_CHANCE {
+            Weather::Rain
+        } else if roll < WIND_CHANCE + RAIN_CHANCE + LIGHTNING_CHANCE {
+            Weather::Lightning
+        } else if roll < WIND_CHANCE + RAIN_CHANCE + LIGHTNING_CHANCE + METEOR_CHANCE {
+            Weather::Meteors
+        } else {
+            Weather::Clear
+        }
+    }
+
+    /// for the HUD, directions as `side` sees them
+    pub fn describe(&self, side: PlayerSide) -> String {
+        match self {
+            Weather::Clear => "Clear skies".to_string(),
+            Weather::Wind { force } => {
+                // the camera is turned around for the top player
+                let towards = if (*force > 0) != side.flip_y() {
+                    "right"
+                } else {
+                    "left"
+                };
+                let strength = force.abs() * 10 / MAX_WIND;
+                format!("Wind {strength}/10 blowing shots {towards}")
+            }
+            Weather::Rain => "Rain: fires are out and the dirt is soft".to_string(),
+            Weather::Lightning => "Thunderstorm: lightning struck something tall".to_string(),
+            Weather::Meteors => "Meteor shower on both sides".to_string(),
+        }
+    }
+}
+
+/// Dirt softened by rain, it takes more damage.
+#[derive(Component)]
+pub struct Soaked;
+
+/// A rock from a meteor shower, nobody's shot.
+#[derive(Component)]
+pub struct Meteor;
+
+/// roll new weather when a turn starts
+pub fn change_weather(
+    mut turns: EventReader<TurnStarted>,
+    settings: Res<WeatherSettings>,
+    mut rng: ResMut<GameRng>,
+    mut weather: ResMut<Weather>,
+) {
+    if turns.read().count() == 0 {
+        return;
+    }
+    *weather = if settings.enabled {
+        Weather::roll(rng.stream(RngStream::Weather))
+    } else {
+        Weather::Clear
+    };
+}
+
+/// push every shot in the air along with the wind
+pub fn blow_wind(weather: Res<Weather>, mut shots: Query<&mut Velocity, With<CannonBall>>) {
+    let Weather::Wind { force } = *weather else {
+        return;
+    };
+    for mut velocity in &mut shots {
+        velocity.x += force;
+    }
+}
+
+/// soak the dirt and put out fires when it starts raining, dry the dirt when it stops
+pub fn rain(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    blocks: Query<(Entity, &Block, Has<Soaked>)>,
+    mut burning: Query<(Entity, &mut Breakable), With<Burning>>,
+) {
+    if !weather.is_changed() {
+        return;
+    }
+    let raining = *weather == Weather::Rain;
+    for (e, block, soaked) in &blocks {
+        let soft = matches!(
+            block.block_type,
+            SingleBlockType::Dirt | SingleBlockType::Grass
+        );
+        if soft && raining && !soaked {
+            commands.entity(e).insert(Soaked);
+        } else if soaked && !raining {
+            commands.entity(e).remove::<Soaked>();
+        }
+    }
+    if raining {
+        for (e, mut breakable) in &mut burning {
+            commands.entity(e).remove::<Burning>();
+            // so the burning tint is cleared
+            breakable.set_changed();
+        }
+    }
+}
+
+/// strike one of the tallest structures on the map
+pub fn lightning(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    battlefield: Res<Battlefield>,
+    mut rng: ResMut<GameRng>,
+    mut targets: Query<BreakableTarget>,
+    mut events: DamageEvents,
+) {
+    if !weather.is_changed() || *weather != Weather::Lightning {
+        return;
+    }
+    // how far each structure reaches out of its side's back edge
+    let height = |side: PlayerSide, cell: I64Vec2| match side {
+        PlayerSide::Bottom => cell.y,
+        PlayerSide::Top => battlefield.height as i64 - 1 - cell.y,
+    };
+    let mut tallest: Vec<(i64, Entity)> = targets
+        .iter()
+        .filter(|target| target.structure.is_some() && target.breakable.health > 0)
+        .filter_map(|target| {
+            let side = target.board.player_side;
+            let top = target
+                .grid
+                .positions
+                .iter()
+                .map(|cell| height(side, *cell))
+                .max()?;
+            Some((top, target.entity))
+        })
+        .collect();
+    tallest.sort_by_key(|(height, _)| std::cmp::Reverse(*height));
+    tallest.truncate(LIGHTNING_CANDIDATES);
+    if tallest.is_empty() {
+        return;
+    }
+    let (_, struck) = tallest[rng.stream(RngStream::Weather).gen_range(0..tallest.len())];
+    let Ok(mut target) = targets.get_mut(struck) else {
+        return;
+    };
+
+    // the bolt comes down from the middle of the map
+    let position = target.transform.translation.truncate();
+    let sky = from_grid_coords(&battlefield, I64Vec2::new(0, battlefield.divider as i64)).y;
+    commands.spawn(beam(Vec2::new(position.x, sky), position, LIGHTNING_COLOR));
+    if !damage_target(
+        &mut commands,
+        &mut events,
+        &mut target,
+        LIGHTNING_DAMAGE,
+        None,
+    ) {
+        catch_fire(&mut commands, &target, AmmoType::Lightning);
+    }
+}
+
+/// drop rocks from the middle of the map on both sides
+pub fn meteor_shower(
+    mut commands: Commands,
+    weather: Res<Weather>,
+    battlefield: Res<Battlefield>,
+    mut rng: ResMut<GameRng>,
+) {
+    if !weather.is_changed() || *weather != Weather::Meteors {
+        return;
+    }
+    let rng = rng.stream(RngStream::Weather);
+    let sky = battlefield.divider as i64 * fixed::CELL;
+    let width = battlefield.width as i64 * fixed::CELL;
+    for side in [PlayerSide::Bottom, PlayerSide::Top] {
+        for _ in 0..METEORS_PER_SIDE {
+            let position = I64Vec2::new(rng.gen_range(0..width), sky);
+            let fall = side.down() * METEOR_SPEED
+                + I64Vec2::X * rng.gen_range(-METEOR_DRIFT..=METEOR_DRIFT);
+            commands.spawn((
+                projectile(&battlefield, position, METEOR_COLOR),
+                Velocity(fall),
+                // owned by the other side so it damages the one it lands on
+                CannonBall {
+                    player_side: side.other(),
+                    ammo: AmmoType::Meteor,
+                    origin: position,
+                    last_hit: None,
+                },
+                Collider,
+                Meteor,
+            ));
+        }
+    }
+}